@@ -1,9 +1,10 @@
-use super::super::MaxHeap::MaxHeap::MaxHeap as Maxheap;
-use crate::MaxHeap::MaxHeap::MaxHeap;
+use crate::MaxHeap::MaxHeap::{MaxHeap, OrdF64};
+use crate::TreeWriter::Json::quote;
+use clap::ArgEnum;
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::fmt::format;
 use std::hash::{Hash, Hasher};
-use std::iter::FromIterator;
+use std::io::Write;
 use std::sync::Arc;
 use ABtree::BTree;
 
@@ -24,14 +25,83 @@ impl HeapPair {
             to_index: t,
         }
     }
+
+    /// The key of the pair in the heap, pairs of the same value are
+    /// popped by their indexes so the merges of tied values do not depend
+    /// on the order in which the pairs were pushed
+    pub(crate) fn key(&self, value: f64) -> HeapKey {
+        (OrdF64(value), Reverse((self.from_index, self.to_index)))
+    }
+}
+
+/// The value of a pair and the reversed indexes of it's nodes
+pub(crate) type HeapKey = (OrdF64, Reverse<(NodeIndex, NodeIndex)>);
+
+/// Given the paired values and a list of labels this returns the
+/// symmetric matrix of the values in the order of the labels,
+/// `None` for the missing pairs and the diagonal
+pub(crate) fn dense_matrix(
+    pair_value_dict: &BTree<ArcStr, BTree<ArcStr, f64>>,
+    labels: &[&str],
+) -> Vec<Vec<Option<f64>>> {
+    let pos = labels
+        .iter()
+        .enumerate()
+        .map(|(i, l)| (*l, i))
+        .collect::<HashMap<_, _>>();
+    let mut out = vec![vec![None; labels.len()]; labels.len()];
+    for (from, inner) in pair_value_dict.iter() {
+        for (to, value) in inner.iter() {
+            if let (Some(i), Some(j)) = (pos.get(**from), pos.get(**to)) {
+                if i != j {
+                    out[*i][*j] = Some(*value);
+                    out[*j][*i] = Some(*value);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// A merge whose value was averaged over only part
+/// of the pairs between the two merged clusters
+pub(crate) struct SparseMerge {
+    pub(crate) node_index: NodeIndex,
+    pub(crate) present: usize,
+    pub(crate) total: usize,
+}
+
+/// The format of the merge trace
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TraceFormat {
+    /// Tab separated with a header line
+    Tsv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// Where and how each merge is written while clustering
+struct MergeTrace {
+    writer: Box<dyn Write>,
+    format: TraceFormat,
+    step: usize,
+    /// Heap entries skipped since the last merge because
+    /// one of their nodes was already clustered
+    stale: usize,
 }
 
-#[derive(Eq)]
 pub(crate) struct Node<'content> {
     pub(crate) data: VecDeque<ArcStr<'content>>,
-    parent: Option<NodeIndex>,
-    left: Option<NodeIndex>,
-    right: Option<NodeIndex>,
+    pub(crate) parent: Option<NodeIndex>,
+    /// From left to right, a merge gives two children
+    /// and collapsing branches may give more
+    pub(crate) children: Vec<NodeIndex>,
+    /// The height of the node in the tree, it's 0 for leaf nodes
+    pub(crate) height: f64,
+    /// The length of the branch to the parent
+    pub(crate) length: f64,
+    /// The fraction of resampled trees having the clade of the node
+    pub(crate) support: Option<f64>,
 }
 
 impl<'content> PartialEq for Node<'content> {
@@ -40,6 +110,8 @@ impl<'content> PartialEq for Node<'content> {
     }
 }
 
+impl<'content> Eq for Node<'content> {}
+
 impl<'content> Hash for Node<'content> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         // The order of elements will makes difference
@@ -56,8 +128,10 @@ impl<'content> Node<'content> {
         Node {
             data: v,
             parent: None,
-            left: None,
-            right: None,
+            children: Vec::new(),
+            height: 0.0,
+            length: 0.0,
+            support: None,
         }
     }
 
@@ -67,8 +141,10 @@ impl<'content> Node<'content> {
         Node {
             data: v,
             parent: None,
-            left: None,
-            right: None,
+            children: Vec::new(),
+            height: 0.0,
+            length: 0.0,
+            support: None,
         }
     }
 
@@ -83,13 +159,8 @@ impl<'content> Node<'content> {
     }
 
     #[inline]
-    fn set_left(node: &mut Node, left_index: NodeIndex) {
-        node.left = Some(left_index);
-    }
-
-    #[inline]
-    fn set_right(node: &mut Node, right_index: NodeIndex) {
-        node.right = Some(right_index);
+    fn add_child(node: &mut Node, child_index: NodeIndex) {
+        node.children.push(child_index);
     }
 
     #[inline]
@@ -99,7 +170,7 @@ impl<'content> Node<'content> {
 
     #[inline]
     fn get_parent_idx(node: &Node) -> Option<NodeIndex> {
-        node.parent.clone()
+        node.parent
     }
 
     /// Pop out the str data in the leaf node
@@ -131,10 +202,11 @@ impl<'content> Node<'content> {
         let inner = Node::get_inner_data(node);
         let mut new_n = Node::new_empty();
         Node::add_inner(&mut new_n, inner);
-        new_n = new_n;
-        new_n.right = node.right.clone();
-        new_n.left = node.left.clone();
-        new_n.parent = node.parent.clone();
+        new_n.children = node.children.clone();
+        new_n.parent = node.parent;
+        new_n.height = node.height;
+        new_n.length = node.length;
+        new_n.support = node.support;
         new_n
     }
 }
@@ -146,6 +218,11 @@ pub(crate) struct BinaryT<'content> {
     all_samples: HashSet<ArcStr<'content>>,
     clustered_leaf_nodes: HashSet<ArcStr<'content>>,
     sub_tree_roots_index: HashSet<NodeIndex>,
+    sparse_merges: Vec<SparseMerge>,
+    /// The value of a sample paired with itself, the height
+    /// of a merge is half of the difference to this value
+    self_value: f64,
+    trace: Option<MergeTrace>,
     len: usize,
 }
 
@@ -154,22 +231,31 @@ impl<'content> BinaryT<'content> {
         index_node_d: HashMap<NodeIndex, Node<'content>>,
         node_index_d: HashMap<Node<'content>, NodeIndex>,
         all_samples: HashSet<ArcStr<'content>>,
+        self_value: f64,
     ) -> Self {
         let len = index_node_d.len();
         BinaryT {
             root_index: None,
             index_node_dict: index_node_d,
             node_index_dict: node_index_d,
-            all_samples: all_samples,
+            all_samples,
             clustered_leaf_nodes: HashSet::new(),
             sub_tree_roots_index: HashSet::new(),
-            len: len,
+            sparse_merges: Vec::new(),
+            self_value,
+            trace: None,
+            len,
         }
     }
 
     /// Given two `Node` this method will merging them and generate a
     /// new parent `Node` and finally return the index of parent node
-    fn merge(&mut self, mut left: Node<'content>, mut right: Node<'content>) -> NodeIndex {
+    fn merge(
+        &mut self,
+        mut left: Node<'content>,
+        mut right: Node<'content>,
+        value: f64,
+    ) -> NodeIndex {
         let right_len = Node::inner_data_size(&right);
         let left_len = Node::inner_data_size(&left);
         let left_index = self.node_index_dict.get(&left);
@@ -181,10 +267,13 @@ impl<'content> BinaryT<'content> {
         let right_index = *right_index.unwrap();
         let parent_index: NodeIndex = self.node_index_dict.len() + 1;
         let mut parent = Node::new_empty();
+        parent.height = self.value_to_height(value);
+        left.length = parent.height - left.height;
+        right.length = parent.height - right.height;
         Node::set_parent(&mut left, parent_index);
         Node::set_parent(&mut right, parent_index);
-        Node::set_left(&mut parent, left_index);
-        Node::set_right(&mut parent, right_index);
+        Node::add_child(&mut parent, left_index);
+        Node::add_child(&mut parent, right_index);
         let left_inner = Node::get_inner_data(&left);
         let right_inner = Node::get_inner_data(&right);
         Node::add_inner(&mut parent, left_inner);
@@ -215,7 +304,7 @@ impl<'content> BinaryT<'content> {
     fn get_unclustered_leafs(&self) -> Vec<ArcStr<'content>> {
         self.all_samples
             .difference(&self.clustered_leaf_nodes)
-            .map(|x| x.clone())
+            .cloned()
             .collect()
     }
 
@@ -226,9 +315,10 @@ impl<'content> BinaryT<'content> {
 
     pub(crate) fn updating(
         &mut self,
+        value: f64,
         left_index: NodeIndex,
         right_index: NodeIndex,
-        max_heap: &mut MaxHeap<f64, HeapPair>,
+        max_heap: &mut MaxHeap<HeapKey, HeapPair>,
         pair_value: &BTree<ArcStr<'content>, BTree<ArcStr<'content>, f64>>,
     ) {
         let left_node = self.index_node_dict.get(&left_index);
@@ -241,9 +331,21 @@ impl<'content> BinaryT<'content> {
         let left_is_clustered = Node::get_parent_idx(left_node).is_some();
         let right_is_clustered = Node::get_parent_idx(right_node).is_some();
         if left_is_clustered | right_is_clustered {
+            if let Some(t) = self.trace.as_mut() {
+                t.stale += 1;
+            }
             return;
         }
-        let new_centroid_idx = self.merge(Node::clone(left_node), Node::clone(right_node));
+        let (present, total) = self.count_pairs(left_node, right_node, pair_value);
+        let new_centroid_idx = self.merge(Node::clone(left_node), Node::clone(right_node), value);
+        self.write_trace(value, left_index, right_index, new_centroid_idx);
+        if present < total {
+            self.sparse_merges.push(SparseMerge {
+                node_index: new_centroid_idx,
+                present,
+                total,
+            });
+        }
         let new_centroid = self.get_node(new_centroid_idx);
         let remained_leaf_centroids = self.get_unclustered_leafs();
         if let Some(n) = new_centroid {
@@ -251,21 +353,263 @@ impl<'content> BinaryT<'content> {
                 // The new centroid node contains all the leafs str
                 // so the clustering is done
                 self.root_index = Some(new_centroid_idx);
-                return;
             } else {
                 let new_values = self.calculate_parallel(&remained_leaf_centroids, n, pair_value);
                 new_values.into_iter().for_each(|o| {
                     if let Some(o) = o {
-                        max_heap.insert(o.0, HeapPair::new(o.1, o.2));
+                        let pair = HeapPair::new(o.1, o.2);
+                        max_heap.insert(pair.key(o.0), pair);
                     }
                 });
             }
         }
     }
 
+    /// The value of a sample paired with itself
+    pub(crate) fn self_value(&self) -> f64 {
+        self.self_value
+    }
+
+    /// Writing each merge done by `updating` into `writer`
+    pub(crate) fn set_trace(&mut self, mut writer: Box<dyn Write>, format: TraceFormat) {
+        if format == TraceFormat::Tsv {
+            writeln!(
+                writer,
+                "step\tleft\tright\tparent\tleft_label\tright_label\tvalue\theight\tsize\tstale"
+            )
+            .expect("Failed to write the merge trace");
+        }
+        self.trace = Some(MergeTrace {
+            writer,
+            format,
+            step: 0,
+            stale: 0,
+        });
+    }
+
+    /// Writing one merge into the trace if there is one
+    fn write_trace(
+        &mut self,
+        value: f64,
+        left_index: NodeIndex,
+        right_index: NodeIndex,
+        parent_index: NodeIndex,
+    ) {
+        if self.trace.is_none() {
+            return;
+        }
+        let left_label = self.label(left_index).unwrap_or("");
+        let right_label = self.label(right_index).unwrap_or("");
+        let height = self.height(parent_index);
+        let size = self.size(parent_index);
+        let t = self.trace.as_mut().unwrap();
+        t.step += 1;
+        let line = match t.format {
+            TraceFormat::Tsv => format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                t.step,
+                left_index,
+                right_index,
+                parent_index,
+                left_label,
+                right_label,
+                value,
+                height,
+                size,
+                t.stale
+            ),
+            TraceFormat::Jsonl => format!(
+                "{{\"step\":{},\"left\":{},\"right\":{},\"parent\":{},\"left_label\":{},\"right_label\":{},\"value\":{},\"height\":{},\"size\":{},\"stale\":{}}}",
+                t.step,
+                left_index,
+                right_index,
+                parent_index,
+                quote(left_label),
+                quote(right_label),
+                value,
+                height,
+                size,
+                t.stale
+            ),
+        };
+        writeln!(t.writer, "{}", line).expect("Failed to write the merge trace");
+        t.stale = 0;
+    }
+
+    /// Converting a merge value into the height of the merged node
+    #[inline]
+    fn value_to_height(&self, value: f64) -> f64 {
+        (self.self_value - value) / 2.0
+    }
+
+    /// Given an index of node return it's children,
+    /// the returned Vec is empty for leaf nodes
+    pub(crate) fn children(&self, index: NodeIndex) -> Vec<NodeIndex> {
+        self.get_node(index)
+            .map(|n| n.children.clone())
+            .unwrap_or_default()
+    }
+
+    /// Given an index of node return the index of it's parent
+    pub(crate) fn parent(&self, index: NodeIndex) -> Option<NodeIndex> {
+        self.get_node(index).and_then(Node::get_parent_idx)
+    }
+
+    /// Return the label of a leaf node and `None` for inner nodes
+    pub(crate) fn label(&self, index: NodeIndex) -> Option<&'content str> {
+        self.get_node(index)
+            .filter(|n| n.children.is_empty())
+            .and_then(|n| n.data.front())
+            .map(|x| **x)
+    }
+
+    /// Given a leaf label return the index of the leaf node
+    pub(crate) fn leaf_index(&self, label: &str) -> Option<NodeIndex> {
+        self.index_node_dict
+            .keys()
+            .copied()
+            .find(|idx| self.label(*idx) == Some(label))
+    }
+
+    /// The height of a node, see `value_to_height`
+    pub(crate) fn height(&self, index: NodeIndex) -> f64 {
+        self.get_node(index).map(|n| n.height).unwrap_or(0.0)
+    }
+
+    /// The number of leafs under a node
+    pub(crate) fn size(&self, index: NodeIndex) -> usize {
+        self.get_node(index).map(Node::inner_data_size).unwrap_or(0)
+    }
+
+    /// The length of the branch from a node to it's parent,
+    /// `None` for the root node
+    pub(crate) fn branch_length(&self, index: NodeIndex) -> Option<f64> {
+        self.get_node(index)
+            .filter(|n| n.parent.is_some())
+            .map(|n| n.length)
+    }
+
+    /// The labels of all the leafs in the order of left to right
+    pub(crate) fn leaf_labels(&self) -> Vec<&'content str> {
+        self.leaves().filter_map(|v| self.label(v.index)).collect()
+    }
+
+    /// Reversing the order of the children of a node, which swaps the
+    /// left and right child of a binary node.
+    /// `refresh_data` must be called after all the swaps
+    pub(crate) fn swap_children(&mut self, index: NodeIndex) {
+        if let Some(n) = self.index_node_dict.get_mut(&index) {
+            n.children.reverse();
+        }
+    }
+
+    /// Rebuilding the data of each inner node from it's children so the
+    /// order of the data follows the order of the children again
+    pub(crate) fn refresh_data(&mut self) {
+        let nodes = self.post_order().collect::<Vec<_>>();
+        for v in nodes {
+            if v.children.is_empty() {
+                continue;
+            }
+            let mut data = VecDeque::new();
+            for c in v.children {
+                data.extend(self.index_node_dict[&c].data.iter().cloned());
+            }
+            self.index_node_dict.get_mut(&v.index).unwrap().data = data;
+        }
+        self.node_index_dict = self
+            .index_node_dict
+            .iter()
+            .map(|(idx, n)| (Node::clone(n), *idx))
+            .collect();
+    }
+
+    /// The roots of the flat clusters in the leaf order, a node is
+    /// split into it's children if `split` returns `true` for it
+    fn flat_clusters<F: Fn(NodeIndex) -> bool>(&self, split: F) -> Vec<NodeIndex> {
+        let mut out = Vec::new();
+        let mut stack: Vec<NodeIndex> = self.root_index.into_iter().collect();
+        while let Some(idx) = stack.pop() {
+            let children = self.children(idx);
+            if !children.is_empty() && split(idx) {
+                stack.extend(children.into_iter().rev());
+            } else {
+                out.push(idx);
+            }
+        }
+        out
+    }
+
+    /// Cutting the tree at a height and returning the roots of the
+    /// flat clusters, which are the highest nodes not above the height
+    pub(crate) fn cut_at_height(&self, height: f64) -> Vec<NodeIndex> {
+        self.flat_clusters(|idx| self.height(idx) > height)
+    }
+
+    /// Cutting the tree into `k` flat clusters by splitting the highest
+    /// cluster until there are `k`, starting from the root so a node is
+    /// never split before it's parent even if their heights are tied.
+    /// Tied clusters are split in the order of their indexes
+    pub(crate) fn cut_into(&self, k: usize) -> Vec<NodeIndex> {
+        let mut split = HashSet::new();
+        let mut frontier: MaxHeap<(OrdF64, Reverse<NodeIndex>), NodeIndex> = MaxHeap::new();
+        let mut clusters = 1;
+        if let Some(root) = self.root_index {
+            frontier.insert((OrdF64(self.height(root)), Reverse(root)), root);
+        }
+        while clusters < k {
+            let idx = match frontier.pop_max() {
+                Some((_, idx)) => idx,
+                None => break,
+            };
+            let children = self.children(idx);
+            if children.is_empty() {
+                continue;
+            }
+            clusters += children.len() - 1;
+            split.insert(idx);
+            for c in children {
+                frontier.insert((OrdF64(self.height(c)), Reverse(c)), c);
+            }
+        }
+        self.flat_clusters(|idx| split.contains(&idx))
+    }
+
+    /// Those merges which were done on part of the pairs
+    pub(crate) fn sparse_merges(&self) -> &Vec<SparseMerge> {
+        &self.sparse_merges
+    }
+
+    /// Given two nodes return the number of pairs between them
+    /// which exist in the `pair_value_dict` and the number of all the pairs
+    fn count_pairs(
+        &self,
+        left_node: &Node,
+        right_node: &Node,
+        pair_value_dict: &BTree<ArcStr<'content>, BTree<ArcStr<'content>, f64>>,
+    ) -> (usize, usize) {
+        let mut present: usize = 0;
+        for l in left_node.data.iter() {
+            for r in right_node.data.iter() {
+                let has_lr = pair_value_dict
+                    .get(l)
+                    .and_then(|inner| inner.get(r))
+                    .is_some();
+                let has_rl = pair_value_dict
+                    .get(r)
+                    .and_then(|inner| inner.get(l))
+                    .is_some();
+                if has_lr | has_rl {
+                    present += 1;
+                }
+            }
+        }
+        (present, left_node.data.len() * right_node.data.len())
+    }
+
     /// Get the clustered sub-tree root nodes
     #[inline]
-    fn get_sub_tree_roots(&self) -> Vec<Option<&Node>> {
+    fn get_sub_tree_roots(&self) -> Vec<Option<&Node<'content>>> {
         self.sub_tree_roots_index
             .iter()
             .map(|idx| self.index_node_dict.get(idx))
@@ -293,19 +637,19 @@ impl<'content> BinaryT<'content> {
         let sub_tree_roots = sub_tree_roots
             .into_iter()
             .map(|x| x.unwrap())
-            .filter(|x| Node::get_parent_idx(*x).is_none())
+            .filter(|x| Node::get_parent_idx(x).is_none())
             .collect::<Vec<_>>();
 
         let mut o1 = remained_leaf_nodes
             .iter()
-            .filter(|x| !Node::is_equal(*x, new_centroid))
+            .filter(|x| !Node::is_equal(x, new_centroid))
             .map(|c| self.calculate_two(c, new_centroid, pair_values))
             .collect::<Vec<_>>();
 
         let o2 = sub_tree_roots
             .iter()
-            .filter(|x| !Node::is_equal(**x, new_centroid))
-            .map(|c| self.calculate_two(*c, new_centroid, pair_values))
+            .filter(|x| !Node::is_equal(x, new_centroid))
+            .map(|c| self.calculate_two(c, new_centroid, pair_values))
             .collect::<Vec<_>>();
         o1.extend(o2);
         o1
@@ -351,48 +695,95 @@ impl<'content> BinaryT<'content> {
 
     /// Give a node return it's index in the `node_index_dict`
     fn get_node_index(&self, node: &Node) -> Option<NodeIndex> {
-        self.node_index_dict.get(node).map(|x| *x)
+        self.node_index_dict.get(node).copied()
     }
 
-    pub(crate) fn to_newick(&self) -> String {
-        if self.root_index.is_none() {
-            panic!("root node is none, which means the clustering is not done");
-        }
-        let root_n = self.get_node(self.root_index.unwrap());
-        let mut nodes: VecDeque<Option<&Node>> = VecDeque::from_iter([root_n]);
-        let mut out = "".to_string();
-        loop {
-            let node = nodes.pop_front();
-            match node {
-                None => {
-                    if !nodes.is_empty() {
-                        continue;
-                    } else {
-                        break out;
+    /// Writing the sub-tree under `index` in Newick format
+    fn write_newick(&self, index: NodeIndex, branch_lengths: bool, out: &mut String) {
+        match self.label(index) {
+            Some(l) => out.push_str(l),
+            None => {
+                out.push('(');
+                for (i, c) in self.children(index).into_iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
                     }
+                    self.write_newick(c, branch_lengths, out);
                 }
-                Some(cur_node) => {
-                    let cur_node = cur_node.unwrap();
-                    let cur_size = Node::inner_data_size(cur_node);
-                    if cur_size < 2 {
-                        continue;
-                    }
-                    let left_idx = cur_node.left.unwrap();
-                    let right_idx = cur_node.right.unwrap();
-                    let left_n = self.get_node(left_idx);
-                    let right_n = self.get_node(right_idx);
-                    nodes.push_back(left_n);
-                    nodes.push_back(right_n);
-                    let inner_data = Node::get_inner_data(cur_node);
-                    let cur_str = inner_data.iter().map(|x| **x).collect::<Vec<_>>().join(",");
-                    if out.len() == 0 {
-                        out = format!("({});", cur_str);
-                    } else {
-                        let replace_str = format!("({})", cur_str);
-                        out = out.replace(cur_str.as_str(), replace_str.as_str());
-                    }
+                out.push(')');
+                // The support is written as the label of the inner node
+                if let Some(s) = self.support(index) {
+                    out.push_str(&s.to_string());
                 }
             }
         }
+        if branch_lengths {
+            if let Some(b) = self.branch_length(index) {
+                out.push_str(&format!(":{}", b));
+            }
+        }
+    }
+
+    /// The tree in Newick format, with `:length` after each
+    /// node but the root if `branch_lengths` is set
+    pub(crate) fn to_newick(&self, branch_lengths: bool) -> String {
+        let root = self
+            .root_index
+            .expect("root node is none, which means the clustering is not done");
+        let mut out = String::new();
+        self.write_newick(root, branch_lengths, &mut out);
+        out.push(';');
+        out
+    }
+}
+
+#[cfg(test)]
+impl<'content> BinaryT<'content> {
+    /// A tree of the leafs `labels` made by the merges in order, the
+    /// merge `(a, b, height)` at `k` gives the node `labels.len() + k`
+    pub(crate) fn from_merges(
+        labels: &[&'content str],
+        merges: &[(NodeIndex, NodeIndex, f64)],
+    ) -> Self {
+        let mut index_node_dict: HashMap<NodeIndex, Node<'content>> = HashMap::new();
+        for (i, l) in labels.iter().enumerate() {
+            index_node_dict.insert(i, Node::new_from_str(Arc::new(*l)));
+        }
+        for (k, (a, b, height)) in merges.iter().enumerate() {
+            let idx = labels.len() + k;
+            let mut node = Node::new_empty();
+            node.children = vec![*a, *b];
+            node.height = *height;
+            index_node_dict.insert(idx, node);
+            for c in [*a, *b] {
+                let child = index_node_dict.get_mut(&c).unwrap();
+                child.parent = Some(idx);
+                child.length = height - child.height;
+            }
+        }
+        let all_samples = labels.iter().map(|l| Arc::new(*l)).collect::<HashSet<_>>();
+        let mut tree = BinaryT::new(index_node_dict, HashMap::new(), all_samples, 0.0);
+        tree.root_index = Some(labels.len() + merges.len() - 1);
+        tree.refresh_data();
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cut_into_tied_heights() {
+        // (a,b) and (a,b,c) are merged at the same height
+        let tree = BinaryT::from_merges(
+            &["a", "b", "c", "d"],
+            &[(0, 1, 0.0), (4, 2, 0.0), (5, 3, 5.0)],
+        );
+        assert_eq!(tree.cut_into(1), vec![6]);
+        assert_eq!(tree.cut_into(2), vec![5, 3]);
+        assert_eq!(tree.cut_into(3), vec![4, 2, 3]);
+        assert_eq!(tree.cut_into(4), vec![0, 1, 2, 3]);
+        assert_eq!(tree.cut_into(5), vec![0, 1, 2, 3]);
     }
 }
@@ -0,0 +1,61 @@
+use super::BinaryTree::{BinaryT, NodeIndex};
+
+impl<'content> BinaryT<'content> {
+    /// Removing the inner nodes other than the root for which `collapse`
+    /// returns `true`, the children of a removed node take it's place
+    /// under it's parent and their branches take the length of the removed
+    /// branch, so the heights are kept. `collapse` sees the tree before any
+    /// change. Returns the number of removed nodes
+    pub(crate) fn collapse_edges<F: Fn(&Self, NodeIndex) -> bool>(&mut self, collapse: F) -> usize {
+        let nodes = self
+            .post_order()
+            .filter(|v| !v.children.is_empty() && self.parent(v.index).is_some())
+            .filter(|v| collapse(self, v.index))
+            .map(|v| v.index)
+            .collect::<Vec<_>>();
+        // Children before parents, so a removed node never
+        // becomes the parent of the children of another one
+        for idx in nodes.iter() {
+            let node = self.index_node_dict.remove(idx).unwrap();
+            let parent = node.parent.unwrap();
+            for c in node.children.iter() {
+                let child = self.node_mut(*c);
+                child.parent = Some(parent);
+                child.length += node.length;
+            }
+            let p = self.node_mut(parent);
+            let pos = p.children.iter().position(|c| c == idx).unwrap();
+            p.children.splice(pos..=pos, node.children);
+        }
+        self.refresh_data();
+        nodes.len()
+    }
+
+    /// Collapsing the inner branches shorter than `length` into
+    /// multifurcating nodes
+    pub(crate) fn collapse_shorter_than(&mut self, length: f64) -> usize {
+        self.collapse_edges(|tree, idx| tree.branch_length(idx).is_some_and(|l| l < length))
+    }
+
+    /// Collapsing the inner branches whose support is below `support`
+    /// into multifurcating nodes, branches without support are kept
+    pub(crate) fn collapse_unsupported(&mut self, support: f64) -> usize {
+        self.collapse_edges(|tree, idx| tree.support(idx).is_some_and(|s| s < support))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapse_only_shorter_branches() {
+        // The branch above (a,b) is 0.5 long, the one above (c,a,b) is 1
+        let mut tree = BinaryT::from_merges(
+            &["a", "b", "c", "d"],
+            &[(0, 1, 1.0), (2, 4, 1.5), (5, 3, 2.5)],
+        );
+        assert_eq!(tree.collapse_shorter_than(1.0), 1);
+        assert_eq!(tree.to_newick(true), "((c:1.5,a:1.5,b:1.5):1,d:2.5);");
+    }
+}
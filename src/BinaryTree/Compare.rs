@@ -0,0 +1,392 @@
+use super::BinaryTree::{BinaryT, NodeIndex};
+use std::collections::{HashMap, HashSet};
+
+/// A set of shared leafs, bit `i` is the `i`-th shared label
+type Bits = Vec<u64>;
+
+/// Metrics between two trees over the leafs both of them have
+pub(crate) struct TreeComparison<'content> {
+    pub(crate) shared: Vec<&'content str>,
+    pub(crate) only_self: usize,
+    pub(crate) only_other: usize,
+    pub(crate) splits: (usize, usize),
+    /// Number of splits in only one of the trees
+    pub(crate) robinson_foulds: usize,
+    /// `robinson_foulds` divided by the number of splits of both trees
+    pub(crate) robinson_foulds_normalized: f64,
+    /// Lowest total cost of pairing the splits of the two trees, where
+    /// the cost of a pair is the number of leafs moved to turn one split
+    /// into the other and an unpaired split is paired with a trivial one
+    pub(crate) matching_split: usize,
+    /// Pearson correlation of the heights of the common ancestors of
+    /// each pair of leafs
+    pub(crate) cophenetic_correlation: f64,
+    /// Goodman-Kruskal gamma of the largest number of clusters
+    /// keeping each pair of leafs together
+    pub(crate) bakers_gamma: f64,
+}
+
+#[inline]
+fn bit_set(bits: &mut Bits, i: usize) {
+    bits[i / 64] |= 1 << (i % 64);
+}
+
+#[inline]
+fn bit_get(bits: &Bits, i: usize) -> bool {
+    bits[i / 64] & (1 << (i % 64)) != 0
+}
+
+#[inline]
+fn bit_count(bits: &Bits) -> usize {
+    bits.iter().map(|w| w.count_ones() as usize).sum()
+}
+
+/// Number of leafs on one side of `a` but the other side of `b`,
+/// the sides of a split are not ordered
+fn split_distance(a: &Bits, b: &Bits, n: usize) -> usize {
+    let moved = a
+        .iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x ^ y).count_ones() as usize)
+        .sum::<usize>();
+    moved.min(n - moved)
+}
+
+/// The lowest total cost of assigning each row to a distinct column
+/// of a square matrix, by the Hungarian method in O(n^3)
+fn min_assignment(cost: &[Vec<usize>]) -> usize {
+    let n = cost.len();
+    let inf = i64::MAX / 2;
+    // Rows and columns are 1-based, column 0 is a virtual start
+    let mut u = vec![0i64; n + 1];
+    let mut v = vec![0i64; n + 1];
+    let mut row_of = vec![0usize; n + 1];
+    let mut way = vec![0usize; n + 1];
+    for row in 1..=n {
+        row_of[0] = row;
+        let mut col = 0;
+        let mut min_to = vec![inf; n + 1];
+        let mut used = vec![false; n + 1];
+        loop {
+            used[col] = true;
+            let r = row_of[col];
+            let mut delta = inf;
+            let mut next = 0;
+            for c in 1..=n {
+                if used[c] {
+                    continue;
+                }
+                let reduced = cost[r - 1][c - 1] as i64 - u[r] - v[c];
+                if reduced < min_to[c] {
+                    min_to[c] = reduced;
+                    way[c] = col;
+                }
+                if min_to[c] < delta {
+                    delta = min_to[c];
+                    next = c;
+                }
+            }
+            for c in 0..=n {
+                if used[c] {
+                    u[row_of[c]] += delta;
+                    v[c] -= delta;
+                } else {
+                    min_to[c] -= delta;
+                }
+            }
+            col = next;
+            if row_of[col] == 0 {
+                break;
+            }
+        }
+        while col != 0 {
+            let prev = way[col];
+            row_of[col] = row_of[prev];
+            col = prev;
+        }
+    }
+    (1..=n).map(|c| cost[row_of[c] - 1][c - 1]).sum()
+}
+
+/// Pearson correlation, NaN if either side is constant
+fn pearson(x: &[f64], y: &[f64]) -> f64 {
+    let n = x.len() as f64;
+    let mx = x.iter().sum::<f64>() / n;
+    let my = y.iter().sum::<f64>() / n;
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for (a, b) in x.iter().zip(y.iter()) {
+        sxy += (a - mx) * (b - my);
+        sxx += (a - mx) * (a - mx);
+        syy += (b - my) * (b - my);
+    }
+    sxy / (sxx * syy).sqrt()
+}
+
+/// Number of pairs `i < j` with `v[i] > v[j]`, sorting `v` on the way
+fn count_inversions(v: &mut Vec<usize>) -> usize {
+    if v.len() < 2 {
+        return 0;
+    }
+    let mut right = v.split_off(v.len() / 2);
+    let mut inversions = count_inversions(v) + count_inversions(&mut right);
+    let mut merged = Vec::with_capacity(v.len() + right.len());
+    let (mut i, mut j) = (0, 0);
+    while i < v.len() && j < right.len() {
+        if v[i] <= right[j] {
+            merged.push(v[i]);
+            i += 1;
+        } else {
+            inversions += v.len() - i;
+            merged.push(right[j]);
+            j += 1;
+        }
+    }
+    merged.extend_from_slice(&v[i..]);
+    merged.extend_from_slice(&right[j..]);
+    *v = merged;
+    inversions
+}
+
+/// Number of pairs inside the runs of equal values of a sorted slice
+fn tied_pairs<T: PartialEq>(sorted: &[T]) -> usize {
+    let mut pairs = 0;
+    let mut run = 0;
+    for i in 0..sorted.len() {
+        if i > 0 && sorted[i] == sorted[i - 1] {
+            run += 1;
+        } else {
+            run = 0;
+        }
+        pairs += run;
+    }
+    pairs
+}
+
+/// Goodman-Kruskal gamma counted in O(n log n) as Knight's algorithm
+/// does for Kendall's tau, NaN if no pair is ordered by both sides
+fn goodman_kruskal_gamma(x: &[usize], y: &[usize]) -> f64 {
+    let mut xy = x.iter().copied().zip(y.iter().copied()).collect::<Vec<_>>();
+    xy.sort_unstable();
+    let n = xy.len();
+    let all = n * n.saturating_sub(1) / 2;
+    let x_ties = tied_pairs(&xy.iter().map(|p| p.0).collect::<Vec<_>>());
+    let joint_ties = tied_pairs(&xy);
+    let mut ys = xy.iter().map(|p| p.1).collect::<Vec<_>>();
+    let discordant = count_inversions(&mut ys);
+    let y_ties = tied_pairs(&ys);
+    let concordant = all + joint_ties - x_ties - y_ties - discordant;
+    (concordant as f64 - discordant as f64) / (concordant + discordant) as f64
+}
+
+impl<'content> BinaryT<'content> {
+    /// The positions in `shared` of the leafs under each node,
+    /// leafs not in `shared` are left out
+    fn shared_leafs(&self, shared: &HashMap<&str, usize>) -> HashMap<NodeIndex, Vec<usize>> {
+        let mut leafs: HashMap<NodeIndex, Vec<usize>> = HashMap::new();
+        for v in self.post_order() {
+            let under = match self.label(v.index) {
+                Some(label) => shared.get(label).copied().into_iter().collect(),
+                None => v
+                    .children
+                    .iter()
+                    .flat_map(|c| leafs[c].iter().copied())
+                    .collect(),
+            };
+            leafs.insert(v.index, under);
+        }
+        leafs
+    }
+
+    /// The non-trivial splits of the shared leafs made by removing a
+    /// branch, each one as the side without the first shared leaf
+    fn splits(&self, leafs: &HashMap<NodeIndex, Vec<usize>>, n: usize) -> HashSet<Bits> {
+        let mut splits = HashSet::new();
+        for (idx, under) in leafs.iter() {
+            if self.parent(*idx).is_none() || under.len() < 2 || under.len() > n - 2 {
+                continue;
+            }
+            let mut bits = vec![0u64; n.div_ceil(64)];
+            under.iter().for_each(|i| bit_set(&mut bits, *i));
+            if bit_get(&bits, 0) {
+                for (i, w) in bits.iter_mut().enumerate() {
+                    let width = (n - i * 64).min(64);
+                    *w = !*w & (u64::MAX >> (64 - width));
+                }
+            }
+            splits.insert(bits);
+        }
+        splits
+    }
+
+    /// The common ancestor of each pair of shared leafs `i < j`,
+    /// at `j * (j - 1) / 2 + i`
+    fn pair_ancestors(&self, leafs: &HashMap<NodeIndex, Vec<usize>>, n: usize) -> Vec<NodeIndex> {
+        let mut ancestors = vec![0; n * n.saturating_sub(1) / 2];
+        for idx in leafs.keys() {
+            let children = self.children(*idx);
+            for (k, a) in children.iter().enumerate() {
+                for b in children[k + 1..].iter() {
+                    for i in leafs[a].iter() {
+                        for j in leafs[b].iter() {
+                            let (i, j) = if i < j { (*i, *j) } else { (*j, *i) };
+                            ancestors[j * (j - 1) / 2 + i] = *idx;
+                        }
+                    }
+                }
+            }
+        }
+        ancestors
+    }
+
+    /// Comparing the topology and the heights of this tree with `other`
+    /// over the leafs both trees have
+    pub(crate) fn compare(&self, other: &BinaryT<'_>) -> TreeComparison<'content> {
+        let mut labels = self.leaf_labels();
+        let other_labels = other.leaf_labels().into_iter().collect::<HashSet<_>>();
+        let total = labels.len();
+        labels.retain(|l| other_labels.contains(l));
+        labels.sort_unstable();
+        let n = labels.len();
+        assert!(
+            n >= 2,
+            "At least two shared leafs are needed to compare the trees, {} found",
+            n
+        );
+        let position = labels
+            .iter()
+            .enumerate()
+            .map(|(i, l)| (*l, i))
+            .collect::<HashMap<_, _>>();
+
+        let leafs = self.shared_leafs(&position);
+        let other_leafs = other.shared_leafs(&position);
+        let splits = self.splits(&leafs, n);
+        let other_splits = other.splits(&other_leafs, n);
+        let robinson_foulds = splits.symmetric_difference(&other_splits).count();
+        let robinson_foulds_normalized = if splits.is_empty() && other_splits.is_empty() {
+            0.0
+        } else {
+            robinson_foulds as f64 / (splits.len() + other_splits.len()) as f64
+        };
+
+        // The missing splits of the smaller side are trivial ones
+        let rows = splits.iter().collect::<Vec<_>>();
+        let cols = other_splits.iter().collect::<Vec<_>>();
+        let size = rows.len().max(cols.len());
+        let cost = (0..size)
+            .map(|r| {
+                (0..size)
+                    .map(|c| match (rows.get(r), cols.get(c)) {
+                        (Some(a), Some(b)) => split_distance(a, b, n),
+                        (Some(s), None) | (None, Some(s)) => bit_count(s),
+                        (None, None) => 0,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let matching_split = min_assignment(&cost);
+
+        let ancestors = self.pair_ancestors(&leafs, n);
+        let other_ancestors = other.pair_ancestors(&other_leafs, n);
+        let heights = ancestors
+            .iter()
+            .map(|a| self.height(*a))
+            .collect::<Vec<_>>();
+        let other_heights = other_ancestors
+            .iter()
+            .map(|a| other.height(*a))
+            .collect::<Vec<_>>();
+        let cophenetic_correlation = pearson(&heights, &other_heights);
+        let bakers_gamma =
+            goodman_kruskal_gamma(&cluster_numbers(&heights), &cluster_numbers(&other_heights));
+
+        TreeComparison {
+            shared: labels,
+            only_self: total - n,
+            only_other: other_labels.len() - n,
+            splits: (splits.len(), other_splits.len()),
+            robinson_foulds,
+            robinson_foulds_normalized,
+            matching_split,
+            cophenetic_correlation,
+            bakers_gamma,
+        }
+    }
+}
+
+/// The largest number of clusters from cutting the tree which keeps a
+/// pair together, given the height of the common ancestor of each pair.
+/// It's one more than the number of distinct merge heights above
+fn cluster_numbers(heights: &[f64]) -> Vec<usize> {
+    let mut distinct = heights.to_vec();
+    distinct.sort_unstable_by(|a, b| b.total_cmp(a));
+    distinct.dedup();
+    heights
+        .iter()
+        .map(|h| distinct.partition_point(|d| d > h) + 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering::Equal;
+
+    #[test]
+    fn min_assignment_of_a_known_matrix() {
+        // Rows 0, 1, 2 to columns 1, 0, 2 cost 1 + 2 + 2
+        let cost = vec![vec![4, 1, 3], vec![2, 0, 5], vec![3, 2, 2]];
+        assert_eq!(min_assignment(&cost), 5);
+    }
+
+    #[test]
+    fn gamma_leaves_out_tied_pairs() {
+        // Pairs (1, 2) and (2, 3) are tied, (0, 1) is discordant
+        // and the other 3 are concordant
+        let x = [1, 2, 2, 3];
+        let y = [2, 1, 3, 3];
+        assert_eq!(goodman_kruskal_gamma(&x, &y), 0.5);
+        assert_eq!(goodman_kruskal_gamma(&x, &x), 1.0);
+        assert!(goodman_kruskal_gamma(&[1, 1], &[1, 2]).is_nan());
+    }
+
+    #[test]
+    fn gamma_matches_counting_each_pair() {
+        let x = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5, 8];
+        let y = [2, 7, 1, 8, 2, 8, 1, 8, 2, 8, 4, 5];
+        let (mut concordant, mut discordant) = (0, 0);
+        for i in 0..x.len() {
+            for j in (i + 1)..x.len() {
+                let (a, b) = (x[i].cmp(&x[j]), y[i].cmp(&y[j]));
+                if a == Equal || b == Equal {
+                    continue;
+                }
+                if a == b {
+                    concordant += 1;
+                } else {
+                    discordant += 1;
+                }
+            }
+        }
+        let expected = (concordant - discordant) as f64 / (concordant + discordant) as f64;
+        assert!((goodman_kruskal_gamma(&x, &y) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn compare_two_quartets() {
+        // ((a,b),(c,d)) against ((a,c),(b,d))
+        let labels = ["a", "b", "c", "d"];
+        let one = BinaryT::from_merges(&labels, &[(0, 1, 1.0), (2, 3, 2.0), (4, 5, 3.0)]);
+        let two = BinaryT::from_merges(&labels, &[(0, 2, 1.0), (1, 3, 2.0), (4, 5, 3.0)]);
+        let c = one.compare(&two);
+        assert_eq!(c.splits, (1, 1));
+        assert_eq!(c.robinson_foulds, 2);
+        assert_eq!(c.robinson_foulds_normalized, 1.0);
+        assert_eq!(c.matching_split, 2);
+        let same = one.compare(&one);
+        assert_eq!(same.robinson_foulds, 0);
+        assert_eq!(same.matching_split, 0);
+        assert!((same.cophenetic_correlation - 1.0).abs() < 1e-12);
+        assert_eq!(same.bakers_gamma, 1.0);
+    }
+}
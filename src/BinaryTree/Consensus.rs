@@ -0,0 +1,278 @@
+use super::BinaryTree::{BinaryT, Node, NodeIndex};
+use super::Support::SplitCounter;
+use clap::ArgEnum;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Which of the counted bipartitions of the leafs are kept in a consensus
+/// tree. The bipartitions don't depend on the roots, so trees differing
+/// only by their root agree
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Consensus {
+    /// The bipartitions of all the trees
+    Strict,
+    /// The bipartitions of more than half of the trees
+    Majority,
+    /// The majority bipartitions, then the other bipartitions from the
+    /// most frequent one as long as each fits with all those kept before it
+    Extended,
+}
+
+/// The number of labels in both of the sorted labels
+fn shared(a: &[&str], b: &[&str]) -> usize {
+    let (mut i, mut j, mut shared) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    shared
+}
+
+/// Whether two sorted clades are disjoint or one contains the other,
+/// which for two sides without the same leaf means that their
+/// bipartitions fit into one tree
+fn compatible(a: &[&str], b: &[&str]) -> bool {
+    let shared = shared(a, b);
+    shared == 0 || shared == a.len() || shared == b.len()
+}
+
+impl<'content> SplitCounter<'content> {
+    /// Building the consensus tree of the counted trees, rooted on the
+    /// root bipartition of the reference tree if it fits the kept ones,
+    /// else on the branch of the first leaf. The height of an inner node
+    /// is the mean height of it's bipartition, raised to the height of
+    /// it's children if needed, and the support is the bipartition
+    /// frequency
+    pub(crate) fn consensus(&self, rule: Consensus, self_value: f64) -> BinaryT<'content> {
+        let trees = self.trees();
+        let leafs = self.leafs();
+        assert!(
+            trees > 0 && leafs.len() > 1,
+            "A consensus needs at least one tree of two leafs"
+        );
+        let mut candidates = self.counted();
+        // The most frequent first, then the larger, then by the labels
+        candidates.sort_by(|a, b| {
+            b.1.cmp(&a.1)
+                .then(b.0.len().cmp(&a.0.len()))
+                .then(a.0.cmp(b.0))
+        });
+        let mut kept: Vec<(&[&'content str], usize, f64)> = Vec::new();
+        for c in candidates {
+            let keep = match rule {
+                Consensus::Strict => c.1 == trees,
+                Consensus::Majority => 2 * c.1 > trees,
+                Consensus::Extended => kept.iter().all(|k| compatible(k.0, c.0)),
+            };
+            if keep {
+                kept.push(c);
+            }
+        }
+        // The root bipartition cuts the leafs into `anchor` and `other`
+        let fits = |side: &[&str]| kept.iter().all(|k| compatible(k.0, side));
+        let anchor = match self.root_side() {
+            side if !side.is_empty() && fits(side) => side.to_vec(),
+            _ => self.other_side(&leafs[..1]),
+        };
+        let other = self.other_side(&anchor);
+        let root_split = kept.iter().find(|k| k.0 == anchor.as_slice());
+
+        // Each kept bipartition becomes the clade of it's side which is in
+        // `anchor` or in `other`, the root is the clade of all the leafs
+        let mut clades = vec![(self.other_side(&[]), None, self.root_height())];
+        for (side, times, height) in kept.iter() {
+            if *side == anchor.as_slice() {
+                continue;
+            }
+            let s = shared(side, &anchor);
+            let clade = if s == 0 || s == side.len() {
+                side.to_vec()
+            } else {
+                self.other_side(side)
+            };
+            clades.push((clade, Some(*times as f64 / trees as f64), *height));
+        }
+        // The two sides of the root bipartition are clades if it's kept or
+        // if it cuts off a single leaf, the support of which is not counted
+        if root_split.is_some() || self.split(&anchor).is_none() {
+            let support = root_split.map(|k| k.1 as f64 / trees as f64);
+            let height = root_split.map_or(0.0, |k| k.2);
+            for side in [anchor, other] {
+                if side.len() > 1 {
+                    clades.push((side, support, height));
+                }
+            }
+        }
+        // Larger clades first so each one finds it's parent among those before
+        clades.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(&b.0)));
+
+        let n = leafs.len();
+        let position = leafs
+            .iter()
+            .enumerate()
+            .map(|(i, l)| (*l, i))
+            .collect::<HashMap<_, _>>();
+        let mut index_node_dict: HashMap<NodeIndex, Node<'content>> = HashMap::new();
+        for (i, l) in leafs.iter().enumerate() {
+            index_node_dict.insert(i, Node::new_from_str(Arc::new(*l)));
+        }
+        // The smallest kept clade holding each leaf so far
+        let mut owner: Vec<Option<NodeIndex>> = vec![None; n];
+        for (k, (clade, support, _)) in clades.iter().enumerate() {
+            let idx = n + k;
+            let mut node = Node::new_empty();
+            node.parent = owner[position[clade[0]]];
+            node.support = *support;
+            index_node_dict.insert(idx, node);
+            for l in clade.iter() {
+                owner[position[l]] = Some(idx);
+            }
+        }
+        for (i, o) in owner.iter().enumerate() {
+            index_node_dict.get_mut(&i).unwrap().parent = *o;
+        }
+
+        // Children in the leaf order of the reference tree
+        let mut children: HashMap<NodeIndex, Vec<(usize, NodeIndex)>> = HashMap::new();
+        for (idx, node) in index_node_dict.iter() {
+            if let Some(p) = node.parent {
+                let first = if *idx < n {
+                    *idx
+                } else {
+                    clades[*idx - n]
+                        .0
+                        .iter()
+                        .map(|l| position[l])
+                        .min()
+                        .unwrap()
+                };
+                children.entry(p).or_default().push((first, *idx));
+            }
+        }
+        for (p, mut c) in children {
+            c.sort_unstable();
+            index_node_dict.get_mut(&p).unwrap().children = c.into_iter().map(|x| x.1).collect();
+        }
+
+        // Smaller clades first so the children are done before their parent
+        for k in (0..clades.len()).rev() {
+            let idx = n + k;
+            let height = index_node_dict[&idx]
+                .children
+                .iter()
+                .map(|c| index_node_dict[c].height)
+                .fold(clades[k].2, f64::max);
+            index_node_dict.get_mut(&idx).unwrap().height = height;
+        }
+        let lengths = index_node_dict
+            .iter()
+            .filter_map(|(idx, node)| {
+                node.parent
+                    .map(|p| (*idx, index_node_dict[&p].height - node.height))
+            })
+            .collect::<Vec<_>>();
+        for (idx, length) in lengths {
+            index_node_dict.get_mut(&idx).unwrap().length = length;
+        }
+
+        let all_samples = leafs.iter().map(|l| Arc::new(*l)).collect::<HashSet<_>>();
+        let mut tree = BinaryT::new(index_node_dict, HashMap::new(), all_samples, self_value);
+        tree.root_index = Some(n);
+        tree.refresh_data();
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compatible_clades() {
+        assert!(compatible(&["a", "b"], &["c", "d"]));
+        assert!(compatible(&["a", "b"], &["a", "b", "c"]));
+        assert!(compatible(&["a", "b", "c"], &["b"]));
+        assert!(!compatible(&["a", "b"], &["b", "c"]));
+        assert!(!compatible(&["a", "b", "d"], &["a", "b", "c"]));
+    }
+
+    #[test]
+    fn consensus_of_three_trees() {
+        // ((a,b),(c,d)), (((a,b),c),d) and ((a,c),(b,d)), so the
+        // bipartition of (a,b) and (c,d) is in two trees and the one of
+        // (a,c) and (b,d) in one
+        let labels = ["a", "b", "c", "d"];
+        let trees = [
+            BinaryT::from_merges(&labels, &[(0, 1, 1.0), (2, 3, 1.0), (4, 5, 2.0)]),
+            BinaryT::from_merges(&labels, &[(0, 1, 1.0), (4, 2, 2.0), (5, 3, 3.0)]),
+            BinaryT::from_merges(&labels, &[(0, 2, 1.0), (1, 3, 1.0), (4, 5, 2.0)]),
+        ];
+        let mut counter = SplitCounter::new(&trees[0]);
+        trees.iter().for_each(|t| counter.add(t));
+
+        let strict = counter.consensus(Consensus::Strict, 0.0);
+        assert_eq!(strict.to_newick(false), "(a,b,c,d);");
+        // Rooted on the root bipartition of the first tree
+        let majority = counter.consensus(Consensus::Majority, 0.0);
+        assert_eq!(
+            majority.to_newick(false),
+            "((a,b)0.6666666666666666,(c,d)0.6666666666666666);"
+        );
+        // The bipartition of (a,c) and (b,d) conflicts with the majority
+        let extended = counter.consensus(Consensus::Extended, 0.0);
+        assert_eq!(extended.to_newick(false), majority.to_newick(false));
+        let cd = extended.lca(&["c", "d"]).unwrap();
+        assert_eq!(extended.height(cd), 1.0);
+        assert_eq!(extended.height(extended.parent(cd).unwrap()), 7.0 / 3.0);
+
+        // The root bipartition of the third tree conflicts with the
+        // majority, so the consensus is rooted on the branch of a
+        let mut counter = SplitCounter::new(&trees[2]);
+        trees.iter().for_each(|t| counter.add(t));
+        let majority = counter.consensus(Consensus::Majority, 0.0);
+        assert_eq!(
+            majority.to_newick(false),
+            "(a,((c,d)0.6666666666666666,b));"
+        );
+    }
+
+    #[test]
+    fn consensus_of_trees_differing_by_their_roots() {
+        // (((a,b),c),(d,e)), ((a,b),(c,(d,e))) and (a,(b,(c,(d,e))))
+        // are the same tree with the root on three branches
+        let labels = ["a", "b", "c", "d", "e"];
+        let trees = [
+            BinaryT::from_merges(
+                &labels,
+                &[(0, 1, 1.0), (5, 2, 2.0), (3, 4, 1.0), (6, 7, 3.0)],
+            ),
+            BinaryT::from_merges(
+                &labels,
+                &[(0, 1, 1.0), (3, 4, 1.0), (2, 6, 2.0), (5, 7, 3.0)],
+            ),
+            BinaryT::from_merges(
+                &labels,
+                &[(3, 4, 1.0), (2, 5, 2.0), (1, 6, 3.0), (0, 7, 4.0)],
+            ),
+        ];
+        let rooted = [
+            "(((a,b)1,c)1,(d,e)1);",
+            "((a,b)1,(c,(d,e)1)1);",
+            "(a,(b,(c,(d,e)1)1));",
+        ];
+        for (reference, newick) in trees.iter().zip(rooted.iter()) {
+            let mut counter = SplitCounter::new(reference);
+            trees.iter().for_each(|t| counter.add(t));
+            for rule in [Consensus::Strict, Consensus::Majority, Consensus::Extended] {
+                assert_eq!(counter.consensus(rule, 0.0).to_newick(false), *newick);
+            }
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use super::BinaryTree::{dense_matrix, ArcStr, BinaryT, NodeIndex};
+use clap::ArgEnum;
+use std::collections::HashMap;
+use ABtree::BTree;
+
+/// The leafs under a node as a range of positions in the leaf order
+#[derive(Clone, Copy)]
+struct Span {
+    first: usize,
+    last: usize,
+}
+
+impl Span {
+    #[inline]
+    fn contains(&self, pos: usize) -> bool {
+        self.first <= pos && pos <= self.last
+    }
+
+    #[inline]
+    fn positions(&self) -> std::ops::RangeInclusive<usize> {
+        self.first..=self.last
+    }
+}
+
+/// The state of the optimal leaf ordering, `cost[i][j]` is the lowest
+/// sum of adjacent distances of the sub-tree under the common ancestor
+/// of the leafs `i` and `j` when it starts with `i` and ends with `j`
+struct Ordering<'t, 'content> {
+    tree: &'t BinaryT<'content>,
+    spans: HashMap<NodeIndex, Span>,
+    distance: Vec<Vec<f64>>,
+    cost: Vec<Vec<f64>>,
+}
+
+impl<'t, 'content> Ordering<'t, 'content> {
+    /// The leafs which may be at the outer end of `node` if `pos` is at
+    /// the other end, which are those of the child not containing `pos`
+    fn outer(&self, node: NodeIndex, pos: usize) -> Span {
+        let children = self.tree.children(node);
+        if children.len() != 2 {
+            return Span {
+                first: pos,
+                last: pos,
+            };
+        }
+        let first = self.spans[&children[0]];
+        if first.contains(pos) {
+            self.spans[&children[1]]
+        } else {
+            first
+        }
+    }
+
+    /// Filling `cost` for the pairs whose common ancestor is `node`
+    fn fill(&mut self, node: NodeIndex) {
+        let children = self.tree.children(node);
+        if children.len() != 2 {
+            return;
+        }
+        let (w, x) = (children[0], children[1]);
+        let (span_w, span_x) = (self.spans[&w], self.spans[&x]);
+        for i in span_w.positions() {
+            let ks = self.outer(w, i);
+            // The best way to step from `i` through some `k` to each `m`
+            let best = span_x
+                .positions()
+                .map(|m| {
+                    ks.positions()
+                        .map(|k| self.cost[i][k] + self.distance[k][m])
+                        .fold(f64::MAX, f64::min)
+                })
+                .collect::<Vec<_>>();
+            for j in span_x.positions() {
+                let ms = self.outer(x, j);
+                let c = ms
+                    .positions()
+                    .map(|m| best[m - span_x.first] + self.cost[m][j])
+                    .fold(f64::MAX, f64::min);
+                self.cost[i][j] = c;
+                self.cost[j][i] = c;
+            }
+        }
+    }
+
+    /// Walking down from `node` which starts with `i` and ends with `j`
+    /// and collecting the nodes whose children must be swapped
+    fn trace_back(&self, node: NodeIndex, i: usize, j: usize, swaps: &mut Vec<NodeIndex>) {
+        let children = self.tree.children(node);
+        if children.len() != 2 {
+            return;
+        }
+        let (mut w, mut x) = (children[0], children[1]);
+        if !self.spans[&w].contains(i) {
+            std::mem::swap(&mut w, &mut x);
+            swaps.push(node);
+        }
+        let ks = self.outer(w, i);
+        let ms = self.outer(x, j);
+        let mut best = (f64::MAX, i, j);
+        for k in ks.positions() {
+            for m in ms.positions() {
+                let c = self.cost[i][k] + self.distance[k][m] + self.cost[m][j];
+                if c < best.0 {
+                    best = (c, k, m);
+                }
+            }
+        }
+        self.trace_back(w, i, best.1, swaps);
+        self.trace_back(x, best.2, j, swaps);
+    }
+}
+
+impl<'content> BinaryT<'content> {
+    /// Flipping the children of the inner nodes so that the sum of the
+    /// distances between adjacent leafs is the lowest, following
+    /// Bar-Joseph et al. (2001) "Fast optimal leaf ordering for
+    /// hierarchical clustering". The distance of a pair is the self value
+    /// minus it's value, and a missing pair takes the largest distance.
+    /// Returns the sum of adjacent distances after the ordering
+    pub(crate) fn optimal_leaf_ordering(
+        &mut self,
+        pair_value_dict: &BTree<ArcStr<'content>, BTree<ArcStr<'content>, f64>>,
+    ) -> f64 {
+        let root = match self.root_index {
+            None => panic!("root node is none, which means the clustering is not done"),
+            Some(r) => r,
+        };
+        assert!(
+            self.index_node_dict.values().all(|n| n.children.len() <= 2),
+            "The optimal leaf ordering needs a binary tree"
+        );
+        let labels = self.leaf_labels();
+        let n = labels.len();
+        let values = dense_matrix(pair_value_dict, &labels);
+        let self_value = self.self_value();
+        let largest = values
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|v| self_value - v)
+            .fold(0.0, f64::max);
+        let distance = values
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|v| v.map_or(largest, |v| self_value - v))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Pre-order, the leafs of a node are contiguous in the leaf order
+        let nodes = self
+            .pre_order_from(root)
+            .map(|v| v.index)
+            .collect::<Vec<_>>();
+        let mut spans: HashMap<NodeIndex, Span> = HashMap::new();
+        let mut next = 0;
+        for idx in nodes.iter().rev() {
+            let children = self.children(*idx);
+            let span = if children.is_empty() {
+                next += 1;
+                Span {
+                    first: n - next,
+                    last: n - next,
+                }
+            } else {
+                Span {
+                    first: children.iter().map(|c| spans[c].first).min().unwrap(),
+                    last: children.iter().map(|c| spans[c].last).max().unwrap(),
+                }
+            };
+            spans.insert(*idx, span);
+        }
+
+        let mut ordering = Ordering {
+            tree: self,
+            spans,
+            distance,
+            cost: vec![vec![0.0; n]; n],
+        };
+        for idx in nodes.iter().rev() {
+            ordering.fill(*idx);
+        }
+
+        let children = self.children(root);
+        let mut swaps = Vec::new();
+        let mut total = 0.0;
+        if children.len() == 2 {
+            let (span_w, span_x) = (ordering.spans[&children[0]], ordering.spans[&children[1]]);
+            let mut best = (f64::MAX, 0, 0);
+            for i in span_w.positions() {
+                for j in span_x.positions() {
+                    if ordering.cost[i][j] < best.0 {
+                        best = (ordering.cost[i][j], i, j);
+                    }
+                }
+            }
+            total = best.0;
+            ordering.trace_back(root, best.1, best.2, &mut swaps);
+        }
+        for idx in swaps {
+            self.swap_children(idx);
+        }
+        self.refresh_data();
+        total
+    }
+}
+
+/// Which side the smaller sub-tree goes when ladderizing
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Ladderize {
+    /// The smaller sub-tree comes first
+    SmallFirst,
+    /// The larger sub-tree comes first
+    LargeFirst,
+}
+
+impl<'content> BinaryT<'content> {
+    /// The smallest leaf label under each node
+    fn min_labels(&self) -> HashMap<NodeIndex, &'content str> {
+        let mut out: HashMap<NodeIndex, &'content str> = HashMap::new();
+        for v in self.post_order() {
+            let label = match self.label(v.index) {
+                Some(l) => l,
+                None => v.children.iter().map(|c| out[c]).min().unwrap_or(""),
+            };
+            out.insert(v.index, label);
+        }
+        out
+    }
+
+    /// Putting the children of each inner node in the order of `key`
+    fn sort_children<K: Ord, F: Fn(NodeIndex) -> K>(&mut self, key: F) {
+        let inner = self
+            .index_node_dict
+            .keys()
+            .copied()
+            .filter(|idx| self.children(*idx).len() > 1)
+            .collect::<Vec<_>>();
+        for idx in inner {
+            let mut children = self.children(idx);
+            // Stable, so the children with equal keys keep their order
+            children.sort_by_key(|c| key(*c));
+            self.node_mut(idx).children = children;
+        }
+        self.refresh_data();
+    }
+
+    /// Ordering the children of each inner node by their
+    /// smallest leaf label, which gives a canonical order
+    pub(crate) fn sort_by_label(&mut self) {
+        let labels = self.min_labels();
+        self.sort_children(|idx| labels[&idx]);
+    }
+
+    /// Ordering the children of each inner node by the number of their
+    /// leafs, ties are broken by the smallest leaf label
+    pub(crate) fn ladderize(&mut self, side: Ladderize) {
+        let labels = self.min_labels();
+        let sizes = labels
+            .keys()
+            .map(|idx| (*idx, self.size(*idx)))
+            .collect::<HashMap<_, _>>();
+        match side {
+            Ladderize::SmallFirst => self.sort_children(|idx| (sizes[&idx], labels[&idx])),
+            Ladderize::LargeFirst => {
+                self.sort_children(|idx| (std::cmp::Reverse(sizes[&idx]), labels[&idx]))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const LABELS: [&str; 5] = ["a", "b", "c", "d", "e"];
+    const DISTANCE: [[f64; 5]; 5] = [
+        [0.0, 2.0, 6.0, 9.0, 4.0],
+        [2.0, 0.0, 5.0, 3.0, 7.0],
+        [6.0, 5.0, 0.0, 3.0, 8.0],
+        [9.0, 3.0, 3.0, 0.0, 5.0],
+        [4.0, 7.0, 8.0, 5.0, 0.0],
+    ];
+    // ((a,b),e) and (c,d) joined at the root
+    const MERGES: [(NodeIndex, NodeIndex, f64); 4] =
+        [(0, 1, 1.0), (2, 3, 1.5), (5, 4, 2.0), (7, 6, 3.0)];
+
+    /// Every leaf order reachable by flipping the children of `node`
+    fn all_orders(node: NodeIndex) -> Vec<Vec<usize>> {
+        if node < LABELS.len() {
+            return vec![vec![node]];
+        }
+        let (a, b, _) = MERGES[node - LABELS.len()];
+        let mut out = Vec::new();
+        for x in all_orders(a).iter() {
+            for y in all_orders(b).iter() {
+                out.push(x.iter().chain(y.iter()).copied().collect());
+                out.push(y.iter().chain(x.iter()).copied().collect());
+            }
+        }
+        out
+    }
+
+    fn adjacent_sum(order: &[usize]) -> f64 {
+        order.windows(2).map(|w| DISTANCE[w[0]][w[1]]).sum()
+    }
+
+    #[test]
+    fn optimal_leaf_ordering_matches_brute_force() {
+        let mut values: BTree<ArcStr, BTree<ArcStr, f64>> = BTree::new(4);
+        for i in 0..LABELS.len() {
+            let mut inner = BTree::new(4);
+            for j in (i + 1)..LABELS.len() {
+                inner.insert(Arc::new(LABELS[j]), -DISTANCE[i][j]);
+            }
+            values.insert(Arc::new(LABELS[i]), inner);
+        }
+        let best = all_orders(LABELS.len() + MERGES.len() - 1)
+            .iter()
+            .map(|o| adjacent_sum(o))
+            .fold(f64::MAX, f64::min);
+
+        let mut tree = BinaryT::from_merges(&LABELS, &MERGES);
+        let total = tree.optimal_leaf_ordering(&values);
+        let order = tree
+            .leaf_labels()
+            .iter()
+            .map(|l| LABELS.iter().position(|x| x == l).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(total, best);
+        assert_eq!(adjacent_sum(&order), best);
+    }
+}
@@ -0,0 +1,79 @@
+use super::BinaryTree::BinaryT;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+impl<'content> BinaryT<'content> {
+    /// Removing the leafs not in `keep`, an inner node left with one child
+    /// is replaced by that child whose branch takes the length of both.
+    /// Returns the number of removed leafs
+    pub(crate) fn prune_to(&mut self, keep: &HashSet<&str>) -> usize {
+        let nodes = self.post_order().collect::<Vec<_>>();
+        let mut removed = 0;
+        for v in nodes {
+            if let Some(label) = self.label(v.index) {
+                if !keep.contains(label) {
+                    self.index_node_dict.remove(&v.index);
+                    removed += 1;
+                }
+                continue;
+            }
+            // The children may have been removed or replaced already
+            let alive = self
+                .children(v.index)
+                .into_iter()
+                .filter(|c| self.index_node_dict.contains_key(c))
+                .collect::<Vec<_>>();
+            match alive[..] {
+                [] => {
+                    self.index_node_dict.remove(&v.index);
+                }
+                [child] => {
+                    let node = self.index_node_dict.remove(&v.index).unwrap();
+                    match node.parent {
+                        Some(p) => self.replace_child(p, v.index, child),
+                        None => self.root_index = Some(child),
+                    }
+                    let c = self.index_node_dict.get_mut(&child).unwrap();
+                    c.parent = node.parent;
+                    c.length = if node.parent.is_some() {
+                        c.length + node.length
+                    } else {
+                        0.0
+                    };
+                }
+                _ => self.node_mut(v.index).children = alive,
+            }
+        }
+        if self
+            .root_index
+            .is_none_or(|r| !self.index_node_dict.contains_key(&r))
+        {
+            panic!("None of the leafs are kept by the pruning");
+        }
+        self.refresh_data();
+        removed
+    }
+
+    /// Renaming the leafs found in `mapping`, the others keep their labels.
+    /// Returns the number of renamed leafs
+    pub(crate) fn relabel(&mut self, mapping: &HashMap<&str, &'content str>) -> usize {
+        let mut renamed = 0;
+        let leafs = self.leaves().map(|v| v.index).collect::<Vec<_>>();
+        for idx in leafs.iter() {
+            let new = match self.label(*idx).and_then(|l| mapping.get(l)) {
+                Some(new) => *new,
+                None => continue,
+            };
+            self.index_node_dict.get_mut(idx).unwrap().data[0] = Arc::new(new);
+            renamed += 1;
+        }
+        let mut seen = HashSet::new();
+        for label in leafs.iter().filter_map(|idx| self.label(*idx)) {
+            if !seen.insert(label) {
+                panic!("The label {} is given to more than one leaf", label);
+            }
+        }
+        self.refresh_data();
+        renamed
+    }
+}
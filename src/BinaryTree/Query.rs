@@ -0,0 +1,109 @@
+use super::BinaryTree::{BinaryT, Node, NodeIndex};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+impl<'content> BinaryT<'content> {
+    /// The nodes from `index` up to the root, both ends included
+    pub(crate) fn path_to_root(&self, index: NodeIndex) -> Vec<NodeIndex> {
+        let mut path = vec![index];
+        while let Some(p) = self.parent(*path.last().unwrap()) {
+            path.push(p);
+        }
+        path
+    }
+
+    /// The lowest node whose clade contains all the labels,
+    /// `None` if any of the labels is not a leaf of the tree
+    pub(crate) fn lca(&self, labels: &[&str]) -> Option<NodeIndex> {
+        let leafs = labels
+            .iter()
+            .map(|l| self.leaf_index(l))
+            .collect::<Option<Vec<_>>>()?;
+        self.common_ancestor(&leafs)
+    }
+
+    /// The labels of the leafs under a node in the order of the tree
+    pub(crate) fn members(&self, index: NodeIndex) -> Vec<&'content str> {
+        self.index_node_dict
+            .get(&index)
+            .map(|n| n.data.iter().map(|x| **x).collect())
+            .unwrap_or_default()
+    }
+
+    /// The clades containing the leaf from the leaf itself up to the root,
+    /// empty if the label is not a leaf of the tree
+    pub(crate) fn clades_of(&self, label: &str) -> Vec<NodeIndex> {
+        self.leaf_index(label)
+            .map(|leaf| self.path_to_root(leaf))
+            .unwrap_or_default()
+    }
+
+    /// A new tree made of the node and all the nodes under it,
+    /// the indexes of the nodes are kept
+    pub(crate) fn subtree(&self, index: NodeIndex) -> BinaryT<'content> {
+        let mut index_node_dict: HashMap<NodeIndex, Node<'content>> = HashMap::new();
+        for v in self.pre_order_from(index) {
+            let node = self
+                .index_node_dict
+                .get(&v.index)
+                .expect("The input index of Node are not exists");
+            index_node_dict.insert(v.index, Node::clone(node));
+        }
+        let root = index_node_dict.get_mut(&index).unwrap();
+        root.parent = None;
+        root.length = 0.0;
+        let node_index_dict = index_node_dict
+            .iter()
+            .map(|(idx, n)| (Node::clone(n), *idx))
+            .collect();
+        let all_samples = self
+            .members(index)
+            .into_iter()
+            .map(Arc::new)
+            .collect::<HashSet<_>>();
+        let mut tree = BinaryT::new(
+            index_node_dict,
+            node_index_dict,
+            all_samples,
+            self.self_value(),
+        );
+        tree.root_index = Some(index);
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (((a,b),c),(d,e)) with the nodes 5 to 8
+    fn tree() -> BinaryT<'static> {
+        BinaryT::from_merges(
+            &["a", "b", "c", "d", "e"],
+            &[(0, 1, 1.0), (5, 2, 2.0), (3, 4, 1.5), (6, 7, 3.0)],
+        )
+    }
+
+    #[test]
+    fn lca_of_leafs() {
+        let tree = tree();
+        assert_eq!(tree.lca(&["a", "b"]), Some(5));
+        assert_eq!(tree.lca(&["b", "c"]), Some(6));
+        assert_eq!(tree.lca(&["a", "e"]), Some(8));
+        assert_eq!(tree.lca(&["c", "a", "b"]), Some(6));
+        assert_eq!(tree.lca(&["d", "d"]), Some(3));
+        assert_eq!(tree.lca(&["d"]), Some(3));
+        assert_eq!(tree.lca(&["a", "x"]), None);
+    }
+
+    #[test]
+    fn clades_and_subtree() {
+        let tree = tree();
+        assert_eq!(tree.clades_of("b"), vec![1, 5, 6, 8]);
+        assert_eq!(tree.clades_of("x"), Vec::<NodeIndex>::new());
+        assert_eq!(tree.members(6), vec!["a", "b", "c"]);
+        let sub = tree.subtree(6);
+        assert_eq!(sub.to_newick(true), "((a:1,b:1):1,c:2);");
+        assert_eq!(sub.leaf_labels(), vec!["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,278 @@
+use super::BinaryTree::{BinaryT, Node, NodeIndex};
+use std::collections::{HashMap, HashSet};
+
+impl<'content> BinaryT<'content> {
+    #[inline]
+    pub(crate) fn node_mut(&mut self, index: NodeIndex) -> &mut Node<'content> {
+        self.index_node_dict
+            .get_mut(&index)
+            .expect("The input index of Node are not exists")
+    }
+
+    /// Replacing the child `old` of `node` with `new`
+    pub(crate) fn replace_child(&mut self, node: NodeIndex, old: NodeIndex, new: NodeIndex) {
+        let n = self.node_mut(node);
+        match n.children.iter().position(|c| *c == old) {
+            Some(pos) => n.children[pos] = new,
+            None => panic!("The node {} is not a child of the node {}", old, node),
+        }
+    }
+
+    /// Recomputing the height of each node as the longest
+    /// distance to the leafs under it
+    pub(crate) fn refresh_heights(&mut self) {
+        let nodes = self.post_order().collect::<Vec<_>>();
+        for v in nodes {
+            let height = v
+                .children
+                .iter()
+                .map(|c| self.index_node_dict[c].height + self.index_node_dict[c].length)
+                .fold(0.0, f64::max);
+            self.node_mut(v.index).height = height;
+        }
+    }
+
+    /// The lowest node whose sub-tree contains all the input nodes
+    pub(crate) fn common_ancestor(&self, nodes: &[NodeIndex]) -> Option<NodeIndex> {
+        let mut nodes = nodes.iter();
+        let mut lca = *nodes.next()?;
+        for n in nodes {
+            let ancestors = self.path_to_root(lca).into_iter().collect::<HashSet<_>>();
+            lca = self
+                .path_to_root(*n)
+                .into_iter()
+                .find(|c| ancestors.contains(c))?;
+        }
+        Some(lca)
+    }
+
+    /// Moving the root onto the branch above `child`, `distance` away from
+    /// `child`. The index of the old root is reused by the new root and
+    /// the old root is dissolved, the lengths of all branches are kept
+    pub(crate) fn reroot_on_edge(&mut self, child: NodeIndex, distance: f64) {
+        let root = self
+            .root_index
+            .expect("root node is none, which means the clustering is not done");
+        let length = self.index_node_dict[&child].length;
+        let distance = distance.max(0.0).min(length);
+        // From `child` up to the root
+        let path = self.path_to_root(child);
+        if path.len() < 2 {
+            panic!("Can not reroot on the branch above the root");
+        }
+        let k = path.len() - 1;
+        let root_children = self.children(root);
+        assert!(
+            root_children.len() == 2,
+            "Can not reroot a tree whose root has {} children",
+            root_children.len()
+        );
+        let other = root_children
+            .into_iter()
+            .find(|c| *c != path[k - 1])
+            .unwrap();
+        let lengths = path
+            .iter()
+            .map(|v| self.index_node_dict[v].length)
+            .collect::<Vec<_>>();
+        let other_length = self.index_node_dict[&other].length;
+
+        if k == 1 {
+            self.node_mut(other).length = other_length + length - distance;
+        } else {
+            // Turning the branches on the path upside down
+            for i in 1..k {
+                let up = if i + 1 == k { other } else { path[i + 1] };
+                self.replace_child(path[i], path[i - 1], up);
+                let n = self.node_mut(path[i]);
+                if i == 1 {
+                    n.parent = Some(root);
+                    n.length = length - distance;
+                } else {
+                    n.parent = Some(path[i - 1]);
+                    n.length = lengths[i - 1];
+                }
+            }
+            let n = self.node_mut(other);
+            n.parent = Some(path[k - 1]);
+            n.length = other_length + lengths[k - 1];
+        }
+        let c = self.node_mut(child);
+        c.parent = Some(root);
+        c.length = distance;
+        let r = self.node_mut(root);
+        r.parent = None;
+        r.length = 0.0;
+        r.children = vec![child, if k == 1 { other } else { path[1] }];
+        self.refresh_heights();
+        self.refresh_data();
+    }
+
+    /// Moving the root onto the leaf, the leaf hangs from
+    /// the new root by a branch of length 0
+    pub(crate) fn reroot_at_leaf(&mut self, label: &str) {
+        let leaf = self
+            .leaf_index(label)
+            .unwrap_or_else(|| panic!("The leaf {} does not exist", label));
+        self.reroot_on_edge(leaf, 0.0);
+    }
+
+    /// Moving the root to the middle of the longest path between two leafs
+    pub(crate) fn midpoint_root(&mut self) {
+        let root = self
+            .root_index
+            .expect("root node is none, which means the clustering is not done");
+        let leaf = {
+            let mut cur = root;
+            while let Some(c) = self.children(cur).first() {
+                cur = *c;
+            }
+            cur
+        };
+        let (a, _, _) = self.farthest(leaf);
+        let (b, total, previous) = self.farthest(a);
+        // Walking back from `b` to `a` to find the branch holding the middle
+        let half = total / 2.0;
+        let mut cur = b;
+        let mut from_a = total;
+        while let Some(next) = previous.get(&cur) {
+            let (next, step) = *next;
+            if from_a - step <= half {
+                // The middle is on the branch between `cur` and `next`
+                if self.parent(cur) == Some(next) {
+                    self.reroot_on_edge(cur, from_a - half);
+                } else {
+                    self.reroot_on_edge(next, half - (from_a - step));
+                }
+                return;
+            }
+            from_a -= step;
+            cur = next;
+        }
+    }
+
+    /// Walking the tree from `start` without directions and returning the
+    /// farthest node, it's distance and the previous step of each node
+    /// as `(node, length of the branch)`
+    fn farthest(&self, start: NodeIndex) -> (NodeIndex, f64, HashMap<NodeIndex, (NodeIndex, f64)>) {
+        let mut previous = HashMap::new();
+        let mut distance: HashMap<NodeIndex, f64> = HashMap::new();
+        distance.insert(start, 0.0);
+        let mut best = (start, 0.0);
+        let mut stack = vec![start];
+        while let Some(cur) = stack.pop() {
+            let d = distance[&cur];
+            let mut neighbours = self
+                .children(cur)
+                .into_iter()
+                .map(|c| (c, self.index_node_dict[&c].length))
+                .collect::<Vec<_>>();
+            if let Some(p) = self.parent(cur) {
+                neighbours.push((p, self.index_node_dict[&cur].length));
+            }
+            for (n, step) in neighbours {
+                if distance.contains_key(&n) {
+                    continue;
+                }
+                distance.insert(n, d + step);
+                previous.insert(n, (cur, step));
+                if d + step > best.1 {
+                    best = (n, d + step);
+                }
+                stack.push(n);
+            }
+        }
+        (best.0, best.1, previous)
+    }
+
+    /// Moving the root onto the middle of the branch above the clade of
+    /// the outgroup, the outgroup must be a clade of the unrooted tree
+    pub(crate) fn outgroup_root(&mut self, labels: &[&str]) {
+        let outgroup = labels
+            .iter()
+            .map(|l| {
+                self.leaf_index(l)
+                    .unwrap_or_else(|| panic!("The leaf {} does not exist", l))
+            })
+            .collect::<HashSet<_>>();
+        let ingroup = self
+            .index_node_dict
+            .keys()
+            .copied()
+            .filter(|idx| self.label(*idx).is_some() && !outgroup.contains(idx))
+            .min();
+        let ingroup = match (outgroup.is_empty(), ingroup) {
+            (false, Some(i)) => i,
+            (_, _) => panic!("The outgroup must hold some but not all of the leafs"),
+        };
+        // With the root next to an ingroup leaf the outgroup can not be
+        // split by the root, so it's a clade if it's monophyletic at all
+        self.reroot_on_edge(ingroup, 0.0);
+        let nodes = outgroup.iter().copied().collect::<Vec<_>>();
+        let lca = self.common_ancestor(&nodes).unwrap();
+        if self.size(lca) != outgroup.len() {
+            panic!("The outgroup is not a clade of the tree");
+        }
+        let length = self.index_node_dict[&lca].length;
+        self.reroot_on_edge(lca, length / 2.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (((a,b),c),(d,e)) with the nodes 5 to 8
+    fn tree() -> BinaryT<'static> {
+        BinaryT::from_merges(
+            &["a", "b", "c", "d", "e"],
+            &[(0, 1, 1.0), (5, 2, 2.0), (3, 4, 1.5), (6, 7, 3.0)],
+        )
+    }
+
+    #[test]
+    fn reroot_at_a_leaf() {
+        let mut tree = tree();
+        tree.reroot_at_leaf("c");
+        assert_eq!(
+            tree.to_newick(true),
+            "(c:0,((a:1,b:1):1,(d:1.5,e:1.5):2.5):2);"
+        );
+        assert_eq!(tree.height(8), 6.0);
+        assert_eq!(tree.parent(8), None);
+    }
+
+    #[test]
+    fn reroot_on_an_outgroup() {
+        let mut tree = tree();
+        tree.outgroup_root(&["e", "d"]);
+        assert_eq!(
+            tree.to_newick(true),
+            "((d:1.5,e:1.5):1.25,((a:1,b:1):1,c:2):1.25);"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The outgroup is not a clade of the tree")]
+    fn outgroup_not_a_clade() {
+        tree().outgroup_root(&["a", "c"]);
+    }
+
+    #[test]
+    fn midpoint_on_a_node() {
+        // The middle of the ultrametric tree is it's root
+        let mut tree = tree();
+        let newick = tree.to_newick(true);
+        tree.midpoint_root();
+        assert_eq!(tree.to_newick(true), newick);
+
+        // The middle of the path from a to c is the node of (a,b)
+        let mut tree = BinaryT::from_merges(&["a", "b", "c"], &[(0, 1, 1.0), (3, 2, 2.0)]);
+        tree.node_mut(0).length = 2.0;
+        tree.node_mut(2).length = 1.0;
+        tree.refresh_heights();
+        tree.midpoint_root();
+        assert_eq!(tree.to_newick(true), "(a:2,(c:2,b:1):0);");
+        assert_eq!(tree.height(4), 2.0);
+    }
+}
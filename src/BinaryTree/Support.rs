@@ -0,0 +1,223 @@
+use super::BinaryTree::{BinaryT, NodeIndex};
+use std::collections::{HashMap, HashSet};
+
+/// Counting the bipartitions of the leafs made by the branches of a set
+/// of trees. A bipartition is written as it's side without the first leaf
+/// of the reference tree, in sorted labels, so the trees differing only by
+/// their root have the same bipartitions
+pub(crate) struct SplitCounter<'content> {
+    /// The bipartitions are restricted to these leafs,
+    /// in the leaf order of the reference tree
+    leafs: Vec<&'content str>,
+    /// The same leafs sorted
+    sorted: Vec<&'content str>,
+    known: HashSet<&'content str>,
+    /// The side without the first leaf of the bipartition made
+    /// by the root of the reference tree
+    root_side: Vec<&'content str>,
+    /// The number of trees having each bipartition and the sum of
+    /// the heights of the node below it's branch
+    counts: HashMap<Vec<&'content str>, (usize, f64)>,
+    /// The sum of the root heights
+    root_heights: f64,
+    trees: usize,
+}
+
+impl<'content> SplitCounter<'content> {
+    /// A counter over the leafs of `reference`
+    pub(crate) fn new(reference: &BinaryT<'content>) -> Self {
+        let leafs = reference.leaf_labels();
+        let mut sorted = leafs.clone();
+        sorted.sort_unstable();
+        let mut counter = SplitCounter {
+            known: leafs.iter().copied().collect(),
+            leafs,
+            sorted,
+            root_side: Vec::new(),
+            counts: HashMap::new(),
+            root_heights: 0.0,
+            trees: 0,
+        };
+        let first_child = reference
+            .root_index
+            .and_then(|r| reference.children(r).first().copied());
+        if let Some(c) = first_child {
+            let mut clade = reference.members(c);
+            clade.sort_unstable();
+            counter.root_side = counter.side(&clade);
+        }
+        counter
+    }
+
+    /// The side without the first leaf of the bipartition
+    /// cut off by the sorted leafs `clade`
+    fn side(&self, clade: &[&'content str]) -> Vec<&'content str> {
+        match self.leafs.first() {
+            Some(first) if clade.binary_search(first).is_ok() => self.other_side(clade),
+            _ => clade.to_vec(),
+        }
+    }
+
+    /// The side without the first leaf of the bipartition cut off by the
+    /// sorted leafs `clade`, which may be either side. `None` if the
+    /// bipartition puts less than two of the leafs on one side
+    pub(crate) fn split(&self, clade: &[&'content str]) -> Option<Vec<&'content str>> {
+        let side = self.side(clade);
+        if side.len() > 1 && side.len() + 1 < self.leafs.len() {
+            Some(side)
+        } else {
+            None
+        }
+    }
+
+    /// The sorted leafs which are not in the sorted leafs `side`
+    pub(crate) fn other_side(&self, side: &[&'content str]) -> Vec<&'content str> {
+        self.sorted
+            .iter()
+            .copied()
+            .filter(|l| side.binary_search(l).is_err())
+            .collect()
+    }
+
+    /// Counting each bipartition of the tree once, the leafs out of the
+    /// counter are left out and a bipartition with less than two leafs on
+    /// one side is not counted. The two branches below a root of two
+    /// children make the same bipartition, which takes the lower height
+    pub(crate) fn add(&mut self, tree: &BinaryT<'content>) {
+        let mut splits: HashMap<Vec<&'content str>, f64> = HashMap::new();
+        for (idx, mut clade) in tree.clades() {
+            if tree.parent(idx).is_none() {
+                self.root_heights += tree.height(idx);
+                continue;
+            }
+            clade.retain(|l| self.known.contains(l));
+            if let Some(split) = self.split(&clade) {
+                let height = splits.entry(split).or_insert(f64::MAX);
+                *height = height.min(tree.height(idx));
+            }
+        }
+        for (split, height) in splits {
+            let c = self.counts.entry(split).or_insert((0, 0.0));
+            c.0 += 1;
+            c.1 += height;
+        }
+        self.trees += 1;
+    }
+
+    /// The number of added trees
+    pub(crate) fn trees(&self) -> usize {
+        self.trees
+    }
+
+    /// The fraction of the added trees having the bipartition
+    pub(crate) fn frequency(&self, split: &[&'content str]) -> f64 {
+        if self.trees == 0 {
+            return 0.0;
+        }
+        self.counts.get(split).map_or(0, |c| c.0) as f64 / self.trees as f64
+    }
+
+    /// The leafs in the leaf order of the reference tree
+    pub(crate) fn leafs(&self) -> &[&'content str] {
+        &self.leafs
+    }
+
+    /// The side without the first leaf of the bipartition made by the
+    /// root of the reference tree, which may cut off a single leaf
+    pub(crate) fn root_side(&self) -> &[&'content str] {
+        &self.root_side
+    }
+
+    /// The mean height of the roots of the added trees
+    pub(crate) fn root_height(&self) -> f64 {
+        self.root_heights / self.trees.max(1) as f64
+    }
+
+    /// Each counted bipartition with the number of trees
+    /// having it and it's mean height in those trees
+    pub(crate) fn counted(&self) -> Vec<(&[&'content str], usize, f64)> {
+        self.counts
+            .iter()
+            .map(|(split, (times, heights))| (split.as_slice(), *times, heights / *times as f64))
+            .collect()
+    }
+}
+
+impl<'content> BinaryT<'content> {
+    /// The sorted leaf labels under each inner node
+    pub(crate) fn clades(&self) -> Vec<(NodeIndex, Vec<&'content str>)> {
+        self.pre_order()
+            .filter(|v| !v.children.is_empty())
+            .map(|v| {
+                let mut clade = self.members(v.index);
+                clade.sort_unstable();
+                (v.index, clade)
+            })
+            .collect()
+    }
+
+    /// Setting the support of each inner node other than the root to the
+    /// frequency of the bipartition made by it's branch in the counted
+    /// trees, a branch cutting off a single leaf gets no support
+    pub(crate) fn set_supports(&mut self, counter: &SplitCounter<'content>) {
+        for (idx, clade) in self.clades() {
+            if self.parent(idx).is_none() {
+                continue;
+            }
+            self.node_mut(idx).support = counter.split(&clade).map(|s| counter.frequency(&s));
+        }
+    }
+
+    /// The support of a node, `None` if it has not been set
+    pub(crate) fn support(&self, index: NodeIndex) -> Option<f64> {
+        self.index_node_dict.get(&index).and_then(|n| n.support)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supports_of_a_rerooted_tree() {
+        // (((a,b),c),(d,e)) and ((a,b),(c,(d,e))) make the same
+        // bipartitions, ((a,c),(b,(d,e))) shares (d,e) with them
+        let labels = ["a", "b", "c", "d", "e"];
+        let trees = [
+            BinaryT::from_merges(
+                &labels,
+                &[(0, 1, 1.0), (5, 2, 2.0), (3, 4, 1.0), (6, 7, 3.0)],
+            ),
+            BinaryT::from_merges(
+                &labels,
+                &[(0, 1, 1.0), (3, 4, 1.0), (2, 6, 2.0), (5, 7, 3.0)],
+            ),
+            BinaryT::from_merges(
+                &labels,
+                &[(0, 2, 1.0), (3, 4, 1.0), (1, 6, 2.0), (5, 7, 3.0)],
+            ),
+        ];
+        let mut reference = BinaryT::from_merges(
+            &labels,
+            &[(0, 1, 1.0), (5, 2, 2.0), (3, 4, 1.0), (6, 7, 3.0)],
+        );
+        reference.reroot_at_leaf("e");
+        let mut counter = SplitCounter::new(&reference);
+        trees.iter().for_each(|t| counter.add(t));
+
+        // e is the first leaf of (e,(d,((a,b),c)))
+        assert_eq!(counter.split(&["a", "b"]), Some(vec!["a", "b"]));
+        assert_eq!(counter.split(&["c", "d", "e"]), Some(vec!["a", "b"]));
+        assert_eq!(counter.split(&["a", "b", "c", "d"]), None);
+        assert_eq!(counter.frequency(&["a", "b"]), 2.0 / 3.0);
+        assert_eq!(counter.frequency(&["a", "b", "c"]), 1.0);
+        assert_eq!(counter.root_height(), 3.0);
+
+        // The branch above (d,(a,b),c) only cuts off e
+        reference.set_supports(&counter);
+        assert_eq!(
+            reference.to_newick(false),
+            "(e,(d,((a,b)0.6666666666666666,c)1));"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use super::BinaryTree::{BinaryT, NodeIndex};
+use std::collections::VecDeque;
+
+/// A node yielded by the traversals of `BinaryT`
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Visit {
+    pub(crate) index: NodeIndex,
+    pub(crate) height: f64,
+    /// From left to right, empty for leaf nodes
+    pub(crate) children: Vec<NodeIndex>,
+}
+
+/// Each node before it's children, children from left to right
+pub(crate) struct PreOrder<'t, 'content> {
+    tree: &'t BinaryT<'content>,
+    stack: Vec<NodeIndex>,
+}
+
+/// Each node after it's children, children from left to right
+pub(crate) struct PostOrder<'t, 'content> {
+    tree: &'t BinaryT<'content>,
+    /// The flag is set once the children of the node are on the stack
+    stack: Vec<(NodeIndex, bool)>,
+}
+
+/// The nodes level by level from the top, each level from left to right
+#[allow(dead_code)]
+pub(crate) struct LevelOrder<'t, 'content> {
+    tree: &'t BinaryT<'content>,
+    queue: VecDeque<NodeIndex>,
+}
+
+/// The leaf nodes from left to right
+pub(crate) struct Leaves<'t, 'content>(PreOrder<'t, 'content>);
+
+impl<'t, 'content> Iterator for PreOrder<'t, 'content> {
+    type Item = Visit;
+
+    fn next(&mut self) -> Option<Visit> {
+        let idx = self.stack.pop()?;
+        let visit = self.tree.visit(idx);
+        self.stack.extend(visit.children.iter().rev());
+        Some(visit)
+    }
+}
+
+impl<'t, 'content> Iterator for PostOrder<'t, 'content> {
+    type Item = Visit;
+
+    fn next(&mut self) -> Option<Visit> {
+        loop {
+            let (idx, expanded) = self.stack.pop()?;
+            let visit = self.tree.visit(idx);
+            if expanded || visit.children.is_empty() {
+                return Some(visit);
+            }
+            self.stack.push((idx, true));
+            self.stack
+                .extend(visit.children.iter().rev().map(|c| (*c, false)));
+        }
+    }
+}
+
+impl<'t, 'content> Iterator for LevelOrder<'t, 'content> {
+    type Item = Visit;
+
+    fn next(&mut self) -> Option<Visit> {
+        let idx = self.queue.pop_front()?;
+        let visit = self.tree.visit(idx);
+        self.queue.extend(visit.children.iter());
+        Some(visit)
+    }
+}
+
+impl<'t, 'content> Iterator for Leaves<'t, 'content> {
+    type Item = Visit;
+
+    fn next(&mut self) -> Option<Visit> {
+        self.0.by_ref().find(|v| v.children.is_empty())
+    }
+}
+
+impl<'content> BinaryT<'content> {
+    #[inline]
+    fn visit(&self, index: NodeIndex) -> Visit {
+        Visit {
+            index,
+            height: self.height(index),
+            children: self.children(index),
+        }
+    }
+
+    /// Pre-order walk of the whole tree
+    pub(crate) fn pre_order(&self) -> PreOrder<'_, 'content> {
+        PreOrder {
+            tree: self,
+            stack: self.root_index.into_iter().collect(),
+        }
+    }
+
+    /// Pre-order walk of the sub-tree under `index`
+    pub(crate) fn pre_order_from(&self, index: NodeIndex) -> PreOrder<'_, 'content> {
+        PreOrder {
+            tree: self,
+            stack: vec![index],
+        }
+    }
+
+    /// Post-order walk of the whole tree
+    pub(crate) fn post_order(&self) -> PostOrder<'_, 'content> {
+        PostOrder {
+            tree: self,
+            stack: self.root_index.into_iter().map(|r| (r, false)).collect(),
+        }
+    }
+
+    /// Post-order walk of the sub-tree under `index`
+    #[allow(dead_code)]
+    pub(crate) fn post_order_from(&self, index: NodeIndex) -> PostOrder<'_, 'content> {
+        PostOrder {
+            tree: self,
+            stack: vec![(index, false)],
+        }
+    }
+
+    /// Level-order walk of the whole tree
+    #[allow(dead_code)]
+    pub(crate) fn level_order(&self) -> LevelOrder<'_, 'content> {
+        LevelOrder {
+            tree: self,
+            queue: self.root_index.into_iter().collect(),
+        }
+    }
+
+    /// Level-order walk of the sub-tree under `index`
+    #[allow(dead_code)]
+    pub(crate) fn level_order_from(&self, index: NodeIndex) -> LevelOrder<'_, 'content> {
+        LevelOrder {
+            tree: self,
+            queue: VecDeque::from(vec![index]),
+        }
+    }
+
+    /// The leafs of the whole tree
+    pub(crate) fn leaves(&self) -> Leaves<'_, 'content> {
+        Leaves(self.pre_order())
+    }
+
+    /// The leafs of the sub-tree under `index`
+    #[allow(dead_code)]
+    pub(crate) fn leaves_from(&self, index: NodeIndex) -> Leaves<'_, 'content> {
+        Leaves(self.pre_order_from(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indexes<I: Iterator<Item = Visit>>(walk: I) -> Vec<NodeIndex> {
+        walk.map(|v| v.index).collect()
+    }
+
+    #[test]
+    fn orders_of_a_known_tree() {
+        // (((a,b),c),(d,e)) with the nodes 5 to 8
+        let tree = BinaryT::from_merges(
+            &["a", "b", "c", "d", "e"],
+            &[(0, 1, 1.0), (5, 2, 2.0), (3, 4, 1.5), (6, 7, 3.0)],
+        );
+        assert_eq!(indexes(tree.pre_order()), vec![8, 6, 5, 0, 1, 2, 7, 3, 4]);
+        assert_eq!(indexes(tree.post_order()), vec![0, 1, 5, 2, 6, 3, 4, 7, 8]);
+        assert_eq!(indexes(tree.level_order()), vec![8, 6, 7, 5, 2, 3, 4, 0, 1]);
+        assert_eq!(indexes(tree.leaves()), vec![0, 1, 2, 3, 4]);
+
+        assert_eq!(indexes(tree.pre_order_from(6)), vec![6, 5, 0, 1, 2]);
+        assert_eq!(indexes(tree.post_order_from(6)), vec![0, 1, 5, 2, 6]);
+        assert_eq!(indexes(tree.level_order_from(6)), vec![6, 5, 2, 0, 1]);
+        assert_eq!(indexes(tree.leaves_from(7)), vec![3, 4]);
+        assert_eq!(indexes(tree.leaves_from(2)), vec![2]);
+
+        let root = tree.pre_order().next().unwrap();
+        assert_eq!(
+            root,
+            Visit {
+                index: 8,
+                height: 3.0,
+                children: vec![6, 7]
+            }
+        );
+    }
+}
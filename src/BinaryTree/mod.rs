@@ -1 +1,11 @@
+#[allow(clippy::module_inception)]
 pub(crate) mod BinaryTree;
+pub(crate) mod Collapse;
+pub(crate) mod Compare;
+pub(crate) mod Consensus;
+pub(crate) mod LeafOrder;
+pub(crate) mod Prune;
+pub(crate) mod Query;
+pub(crate) mod Rooting;
+pub(crate) mod Support;
+pub(crate) mod Traversal;
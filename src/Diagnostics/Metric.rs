@@ -0,0 +1,173 @@
+use crate::MaxHeap::MaxHeap::{MinHeap, OrdF64};
+use crate::Resample::Random::SplitMix64;
+use std::cmp::Reverse;
+
+/// Options of the metric property checks
+pub(crate) struct MetricOptions {
+    /// A property is violated if it's broken by more than this
+    pub(crate) tolerance: f64,
+    /// The number of the worst violations kept for each property
+    pub(crate) report: usize,
+    /// Checking a random sample of this many triplets or quartets
+    /// if there are more of them
+    pub(crate) max_tuples: usize,
+    pub(crate) seed: u64,
+}
+
+/// The samples breaking a property and by how much
+pub(crate) struct Violation<'content> {
+    pub(crate) samples: Vec<&'content str>,
+    pub(crate) excess: f64,
+}
+
+/// How often one property is violated
+pub(crate) struct PropertyReport<'content> {
+    pub(crate) name: &'static str,
+    /// The number of samples in a tuple, 3 or 4
+    pub(crate) size: usize,
+    /// The number of checked triplets or quartets
+    pub(crate) checked: usize,
+    pub(crate) violated: usize,
+    /// Whether the checked tuples are a random sample of all of them
+    pub(crate) sampled: bool,
+    /// The worst violations, the largest excess first
+    pub(crate) worst: Vec<Violation<'content>>,
+}
+
+/// The number of ways to choose `k` of `n`, saturating at `usize::MAX`
+fn choose(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    let mut c: u128 = 1;
+    for i in 0..k {
+        c = c * (n - i) as u128 / (i + 1) as u128;
+        if c > usize::MAX as u128 {
+            return usize::MAX;
+        }
+    }
+    c as usize
+}
+
+/// Calling `f` on each set of `k` of `0..n` in increasing order, or on
+/// `max` random sets if there are more. Returns whether it was sampled
+fn for_each_tuple<F: FnMut(&[usize])>(
+    n: usize,
+    k: usize,
+    max: usize,
+    rng: &mut SplitMix64,
+    mut f: F,
+) -> bool {
+    if n < k {
+        return false;
+    }
+    if choose(n, k) > max {
+        let mut tuple = Vec::with_capacity(k);
+        for _ in 0..max {
+            tuple.clear();
+            while tuple.len() < k {
+                let i = rng.below(n);
+                if !tuple.contains(&i) {
+                    tuple.push(i);
+                }
+            }
+            tuple.sort_unstable();
+            f(&tuple);
+        }
+        return true;
+    }
+    let mut tuple = (0..k).collect::<Vec<_>>();
+    loop {
+        f(&tuple);
+        // The rightmost index which can still move forward
+        let pos = match (0..k).rev().find(|i| tuple[*i] < n - k + *i) {
+            Some(p) => p,
+            None => return false,
+        };
+        tuple[pos] += 1;
+        for i in (pos + 1)..k {
+            tuple[i] = tuple[i - 1] + 1;
+        }
+    }
+}
+
+/// Checking every `k` samples with `excess`, which returns by how much
+/// they break the property or `None` if a pair of them is missing
+fn check<'content, F: Fn(&[usize]) -> Option<f64>>(
+    name: &'static str,
+    labels: &[&'content str],
+    k: usize,
+    options: &MetricOptions,
+    excess: F,
+) -> PropertyReport<'content> {
+    let mut rng = SplitMix64::new(options.seed);
+    let mut checked = 0;
+    let mut violated = 0;
+    // The smallest of the kept violations is on the top,
+    // of equal ones the later tuple is dropped first
+    let mut worst: MinHeap<(OrdF64, Reverse<Vec<usize>>), ()> = MinHeap::new();
+    let sampled = for_each_tuple(labels.len(), k, options.max_tuples, &mut rng, |t| {
+        let e = match excess(t) {
+            Some(e) => e,
+            None => return,
+        };
+        checked += 1;
+        if e <= options.tolerance {
+            return;
+        }
+        violated += 1;
+        worst.insert((OrdF64(e), Reverse(t.to_vec())), ());
+        if worst.len() > options.report {
+            worst.pop_min();
+        }
+    });
+    let mut worst = worst
+        .into_sorted_vec()
+        .into_iter()
+        .map(|((e, t), _)| Violation {
+            samples: t.0.iter().map(|i| labels[*i]).collect(),
+            excess: e.0,
+        })
+        .collect::<Vec<_>>();
+    worst.reverse();
+    PropertyReport {
+        name,
+        size: k,
+        checked,
+        violated,
+        sampled,
+        worst,
+    }
+}
+
+/// The difference of the largest and the second largest value
+fn top_gap(mut v: [f64; 3]) -> f64 {
+    v.sort_unstable_by(|a, b| a.total_cmp(b));
+    v[2] - v[1]
+}
+
+/// Checking the triangle inequality, the ultrametric inequality and the
+/// four-point condition on the distances between the samples, `distance`
+/// is symmetric and `None` for the missing pairs
+pub(crate) fn check_metric<'content>(
+    labels: &[&'content str],
+    distance: &[Vec<Option<f64>>],
+    options: &MetricOptions,
+) -> Vec<PropertyReport<'content>> {
+    let d = |i: usize, j: usize| distance[i][j];
+    let triangle = check("triangle inequality", labels, 3, options, |t| {
+        let (a, b, c) = (d(t[0], t[1])?, d(t[0], t[2])?, d(t[1], t[2])?);
+        Some((a - b - c).max(b - a - c).max(c - a - b))
+    });
+    let ultrametric = check("ultrametric inequality", labels, 3, options, |t| {
+        Some(top_gap([d(t[0], t[1])?, d(t[0], t[2])?, d(t[1], t[2])?]))
+    });
+    let four_point = check("four-point condition", labels, 4, options, |t| {
+        Some(top_gap([
+            d(t[0], t[1])? + d(t[2], t[3])?,
+            d(t[0], t[2])? + d(t[1], t[3])?,
+            d(t[0], t[3])? + d(t[1], t[2])?,
+        ]))
+    });
+    vec![triangle, ultrametric, four_point]
+}
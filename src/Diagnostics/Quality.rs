@@ -0,0 +1,197 @@
+use crate::BinaryTree::BinaryTree::{BinaryT, NodeIndex};
+use std::collections::HashMap;
+
+/// Scores of a flat clustering of the samples by their distances
+pub(crate) struct ClusterQuality {
+    /// The number of clusters
+    pub(crate) k: usize,
+    /// The silhouette of each sample, 0 for a sample alone in it's
+    /// cluster and NaN if none of it's pairs exist
+    pub(crate) silhouettes: Vec<f64>,
+    /// The mean of the silhouettes which are not NaN
+    pub(crate) silhouette: f64,
+    /// The smallest distance between two clusters divided by
+    /// the largest distance inside a cluster
+    pub(crate) dunn: f64,
+    /// The Davies-Bouldin index with each cluster represented by it's
+    /// medoid, the scatter of a cluster is the mean distance of it's other
+    /// members to the medoid and the separation of two clusters is the
+    /// distance between their medoids
+    pub(crate) davies_bouldin: f64,
+}
+
+/// The mean of the values, `None` if there are none
+fn mean<I: Iterator<Item = f64>>(values: I) -> Option<f64> {
+    let (sum, n) = values.fold((0.0, 0usize), |(s, n), v| (s + v, n + 1));
+    if n == 0 {
+        None
+    } else {
+        Some(sum / n as f64)
+    }
+}
+
+/// Scoring the clustering putting sample `i` into cluster `cluster[i]`
+/// of `0..k`, `distance` is symmetric and `None` for the missing pairs,
+/// which are left out of every mean, minimum and maximum
+pub(crate) fn cluster_quality(
+    distance: &[Vec<Option<f64>>],
+    cluster: &[usize],
+    k: usize,
+) -> ClusterQuality {
+    let n = cluster.len();
+    let mut members = vec![Vec::new(); k];
+    for (i, c) in cluster.iter().enumerate() {
+        members[*c].push(i);
+    }
+    // The mean distance of sample `i` to the members of cluster `c`
+    let mean_to = |i: usize, c: usize| {
+        mean(
+            members[c]
+                .iter()
+                .filter(|j| **j != i)
+                .filter_map(|j| distance[i][*j]),
+        )
+    };
+
+    let silhouettes = (0..n)
+        .map(|i| {
+            let own = cluster[i];
+            if members[own].len() == 1 {
+                return 0.0;
+            }
+            let nearest = (0..k)
+                .filter(|c| *c != own)
+                .filter_map(|c| mean_to(i, c))
+                .min_by(|a, b| a.total_cmp(b));
+            match (mean_to(i, own), nearest) {
+                (Some(a), Some(b)) => (b - a) / a.max(b),
+                (_, _) => f64::NAN,
+            }
+        })
+        .collect::<Vec<_>>();
+    let silhouette = mean(silhouettes.iter().copied().filter(|s| !s.is_nan())).unwrap_or(f64::NAN);
+
+    let mut diameter: f64 = 0.0;
+    let mut separation = f64::INFINITY;
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if let Some(d) = distance[i][j] {
+                if cluster[i] == cluster[j] {
+                    diameter = diameter.max(d);
+                } else {
+                    separation = separation.min(d);
+                }
+            }
+        }
+    }
+    let dunn = if k < 2 || separation.is_infinite() {
+        f64::NAN
+    } else {
+        separation / diameter
+    };
+
+    let medoids = members
+        .iter()
+        .enumerate()
+        .map(|(c, m)| {
+            m.iter()
+                .map(|i| (*i, mean_to(*i, c).unwrap_or(0.0)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let worst_ratios = (0..k).filter_map(|c| {
+        (0..k)
+            .filter(|o| *o != c)
+            .filter_map(|o| {
+                let d = distance[medoids[c].0][medoids[o].0]?;
+                Some((medoids[c].1 + medoids[o].1) / d)
+            })
+            .max_by(|a, b| a.total_cmp(b))
+    });
+    let davies_bouldin = mean(worst_ratios).unwrap_or(f64::NAN);
+
+    ClusterQuality {
+        k,
+        silhouettes,
+        silhouette,
+        dunn,
+        davies_bouldin,
+    }
+}
+
+/// The cluster of each of the sorted `labels` given the roots of the flat
+/// clusters, which is the position of it's root in `roots`
+pub(crate) fn assign_clusters(tree: &BinaryT, labels: &[&str], roots: &[NodeIndex]) -> Vec<usize> {
+    let position = labels
+        .iter()
+        .enumerate()
+        .map(|(i, l)| (*l, i))
+        .collect::<HashMap<_, _>>();
+    let mut cluster = vec![0; labels.len()];
+    for (c, r) in roots.iter().enumerate() {
+        for m in tree.members(*r) {
+            cluster[position[m]] = c;
+        }
+    }
+    cluster
+}
+
+/// Scoring the cuts of the tree into each number of clusters from 2 to
+/// `max`, a number which no cut gives is left out
+pub(crate) fn sweep_k(
+    tree: &BinaryT,
+    labels: &[&str],
+    distance: &[Vec<Option<f64>>],
+    max: usize,
+) -> Vec<ClusterQuality> {
+    let mut out = (2..=max.min(labels.len()))
+        .map(|k| tree.cut_into(k))
+        .map(|roots| {
+            cluster_quality(
+                distance,
+                &assign_clusters(tree, labels, &roots),
+                roots.len(),
+            )
+        })
+        .collect::<Vec<_>>();
+    // Splitting a multifurcating node gives more than one cluster more
+    out.dedup_by_key(|q| q.k);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep_tied_heights() {
+        // a, b and c are at distance 0 of each other and 10 of d
+        let tree = BinaryT::from_merges(
+            &["a", "b", "c", "d"],
+            &[(0, 1, 0.0), (4, 2, 0.0), (5, 3, 5.0)],
+        );
+        let labels = ["a", "b", "c", "d"];
+        let distance = (0..4)
+            .map(|i| {
+                (0..4)
+                    .map(|j| {
+                        Some(if i == j || (i < 3 && j < 3) {
+                            0.0
+                        } else {
+                            10.0
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let sweep = sweep_k(&tree, &labels, &distance, 10);
+        assert_eq!(sweep.iter().map(|q| q.k).collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(sweep[0].silhouettes, vec![1.0, 1.0, 1.0, 0.0]);
+        assert_eq!(sweep[0].silhouette, 0.75);
+        assert_eq!(
+            assign_clusters(&tree, &labels, &tree.cut_into(3)),
+            vec![0, 0, 1, 2]
+        );
+    }
+}
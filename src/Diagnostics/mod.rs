@@ -0,0 +1,2 @@
+pub(crate) mod Metric;
+pub(crate) mod Quality;
@@ -1,10 +1,59 @@
+use std::cmp::Ordering;
 use std::collections::VecDeque;
+use std::iter::FromIterator;
 
-pub struct MaxHeap<K, V> {
+/// Decides which of two keys should sit closer to the top of a `Heap`
+pub trait HeapOrder<K> {
+    /// Return `true` if `a` must be popped before `b`
+    fn higher(&self, a: &K, b: &K) -> bool;
+}
+
+/// The largest key is popped first
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MaxOrder;
+
+/// The smallest key is popped first
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MinOrder;
+
+/// A custom comparator, `f(a, b)` returns `true`
+/// if `a` must be popped before `b`
+#[derive(Clone, Copy, Debug)]
+#[allow(dead_code)]
+pub struct FnOrder<F>(pub F);
+
+impl<K: PartialOrd> HeapOrder<K> for MaxOrder {
+    #[inline]
+    fn higher(&self, a: &K, b: &K) -> bool {
+        a > b
+    }
+}
+
+impl<K: PartialOrd> HeapOrder<K> for MinOrder {
+    #[inline]
+    fn higher(&self, a: &K, b: &K) -> bool {
+        a < b
+    }
+}
+
+impl<K, F: Fn(&K, &K) -> bool> HeapOrder<K> for FnOrder<F> {
+    #[inline]
+    fn higher(&self, a: &K, b: &K) -> bool {
+        (self.0)(a, b)
+    }
+}
+
+/// A binary heap of key-value pairs whose ordering
+/// is decided by `O`
+pub struct Heap<K, V, O = MaxOrder> {
     data: VecDeque<(K, V)>,
+    order: O,
 }
 
-impl<K: PartialOrd, V> MaxHeap<K, V> {
+pub type MaxHeap<K, V> = Heap<K, V, MaxOrder>;
+pub type MinHeap<K, V> = Heap<K, V, MinOrder>;
+
+impl<K, V, O: HeapOrder<K>> Heap<K, V, O> {
     fn _parent_idx(&self, idx: usize) -> Option<usize> {
         if idx == 0 {
             None
@@ -21,6 +70,11 @@ impl<K: PartialOrd, V> MaxHeap<K, V> {
         idx * 2 + 2
     }
 
+    #[inline]
+    fn _higher(&self, a: usize, b: usize) -> bool {
+        self.order.higher(&self.data[a].0, &self.data[b].0)
+    }
+
     fn _sift_up(&mut self, mut idx: usize) {
         loop {
             if idx == 0 {
@@ -28,17 +82,12 @@ impl<K: PartialOrd, V> MaxHeap<K, V> {
             }
             let op_parent_idx = self._parent_idx(idx);
             match op_parent_idx {
-                None => {
-                    break;
+                Some(parent_idx) if self._higher(idx, parent_idx) => {
+                    self.data.swap(parent_idx, idx);
+                    idx = parent_idx;
                 }
-                Some(parent_idx) => {
-                    if self.data[parent_idx].0 < self.data[idx].0 {
-                        self.data.swap(parent_idx, idx);
-                        idx = parent_idx;
-                        continue;
-                    } else {
-                        break;
-                    }
+                _ => {
+                    break;
                 }
             }
         }
@@ -51,27 +100,23 @@ impl<K: PartialOrd, V> MaxHeap<K, V> {
             let has_left = left_idx < self.data.len();
             let has_right = right_idx < self.data.len();
 
-            if !has_left && !has_right {
+            if !has_left {
                 break;
-            } else if !has_left && has_right {
-                break;
-            } else if has_left && !has_right {
-                if self.data[idx].0 < self.data[left_idx].0 {
+            } else if !has_right {
+                if self._higher(left_idx, idx) {
                     self.data.swap(idx, left_idx);
-                    break;
-                } else {
-                    break;
                 }
+                break;
             } else {
-                let max_child_idx = if self.data[left_idx].0 > self.data[right_idx].0 {
+                let top_child_idx = if self._higher(left_idx, right_idx) {
                     left_idx
                 } else {
                     right_idx
                 };
 
-                if self.data[idx].0 < self.data[max_child_idx].0 {
-                    self.data.swap(idx, max_child_idx);
-                    idx = max_child_idx;
+                if self._higher(top_child_idx, idx) {
+                    self.data.swap(idx, top_child_idx);
+                    idx = top_child_idx;
                     continue;
                 } else {
                     break;
@@ -79,34 +124,244 @@ impl<K: PartialOrd, V> MaxHeap<K, V> {
             }
         }
     }
+
+    /// Restore the heap property for the whole data in O(n)
+    fn _heapify(&mut self) {
+        let n = self.len();
+        if n < 2 {
+            return;
+        }
+        for idx in (0..n / 2).rev() {
+            self._sift_down(idx);
+        }
+    }
 }
 
-impl<K: PartialOrd, V> MaxHeap<K, V> {
+impl<K, V, O: HeapOrder<K>> Heap<K, V, O> {
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
-    pub fn new() -> Self {
-        MaxHeap {
-            data: VecDeque::<(K, V)>::new(),
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Create an empty heap with the given ordering
+    pub fn with_order(order: O) -> Self {
+        Heap {
+            data: VecDeque::new(),
+            order,
         }
     }
 
+    /// Build a heap from a vector in O(n)
+    pub fn from_vec_with_order(v: Vec<(K, V)>, order: O) -> Self {
+        let mut heap = Heap {
+            data: VecDeque::from(v),
+            order,
+        };
+        heap._heapify();
+        heap
+    }
+
     pub fn insert(&mut self, k: K, v: V) {
         self.data.push_back((k, v));
         self._sift_up(self.len() - 1);
     }
 
-    pub fn pop_max(&mut self) -> Option<(K, V)> {
-        if self.len() == 0 {
+    /// Look at the top key-value pair without removing it
+    #[allow(dead_code)]
+    pub fn peek(&self) -> Option<(&K, &V)> {
+        self.data.front().map(|(k, v)| (k, v))
+    }
+
+    /// Pop out the top key-value pair according to the ordering
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        if self.is_empty() {
             None
         } else {
             // first swap then pop out
-            self.data.swap(0, self.len() - 1);
-            self.data.pop_back().map(|o| {
+            let last = self.len() - 1;
+            self.data.swap(0, last);
+            self.data.pop_back().inspect(|_| {
                 self._sift_down(0);
-                o
             })
         }
     }
+
+    /// Consume the heap and return the pairs in popping order
+    pub fn into_sorted_vec(mut self) -> Vec<(K, V)> {
+        let mut out = Vec::with_capacity(self.len());
+        while let Some(o) = self.pop() {
+            out.push(o);
+        }
+        out
+    }
+
+    /// Iterate over the pairs in arbitrary order
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = &(K, V)> {
+        self.data.iter()
+    }
+
+    #[allow(dead_code)]
+    pub fn clear(&mut self) {
+        self.data.clear()
+    }
+}
+
+impl<K, V, O: HeapOrder<K> + Default> Heap<K, V, O> {
+    pub fn new() -> Self {
+        Heap::with_order(O::default())
+    }
+
+    /// Build a heap from a vector in O(n)
+    pub fn from_vec(v: Vec<(K, V)>) -> Self {
+        Heap::from_vec_with_order(v, O::default())
+    }
+}
+
+impl<K: PartialOrd, V> Heap<K, V, MaxOrder> {
+    pub fn pop_max(&mut self) -> Option<(K, V)> {
+        self.pop()
+    }
+}
+
+impl<K: PartialOrd, V> Heap<K, V, MinOrder> {
+    pub fn pop_min(&mut self) -> Option<(K, V)> {
+        self.pop()
+    }
+}
+
+impl<K, V, O: HeapOrder<K> + Default> Default for Heap<K, V, O> {
+    fn default() -> Self {
+        Heap::new()
+    }
+}
+
+impl<K, V, O: HeapOrder<K> + Default> FromIterator<(K, V)> for Heap<K, V, O> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Heap::from_vec(iter.into_iter().collect())
+    }
+}
+
+impl<K, V, O: HeapOrder<K>> Extend<(K, V)> for Heap<K, V, O> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+/// Consuming the heap yields the pairs in arbitrary order,
+/// use `into_sorted_vec` for the popping order
+impl<K, V, O> IntoIterator for Heap<K, V, O> {
+    type Item = (K, V);
+    type IntoIter = std::collections::vec_deque::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.into_iter()
+    }
+}
+
+/// A `f64` wrapper with a total order so that a NaN
+/// can not silently break the heap invariant.
+/// The ordering follows `f64::total_cmp`, which means
+/// -NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN
+#[derive(Clone, Copy, Debug)]
+pub struct OrdF64(pub f64);
+
+impl PartialEq for OrdF64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.total_cmp(&other.0) == Ordering::Equal
+    }
+}
+
+impl Eq for OrdF64 {}
+
+impl PartialOrd for OrdF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrdF64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl From<f64> for OrdF64 {
+    fn from(v: f64) -> Self {
+        OrdF64(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: [i32; 8] = [5, 1, 8, 3, 9, 2, 7, 3];
+
+    fn keys<O: HeapOrder<i32>>(heap: Heap<i32, usize, O>) -> Vec<i32> {
+        heap.into_sorted_vec().into_iter().map(|(k, _)| k).collect()
+    }
+
+    #[test]
+    fn max_order() {
+        let mut heap: MaxHeap<i32, usize> = MaxHeap::new();
+        heap.extend(KEYS.iter().enumerate().map(|(i, k)| (*k, i)));
+        assert_eq!(heap.len(), 8);
+        assert_eq!(heap.peek(), Some((&9, &4)));
+        assert_eq!(heap.pop_max(), Some((9, 4)));
+        assert_eq!(keys(heap), vec![8, 7, 5, 3, 3, 2, 1]);
+    }
+
+    #[test]
+    fn min_order() {
+        let heap: MinHeap<i32, usize> = KEYS.iter().enumerate().map(|(i, k)| (*k, i)).collect();
+        assert_eq!(keys(heap), vec![1, 2, 3, 3, 5, 7, 8, 9]);
+
+        let mut heap: MinHeap<i32, usize> = MinHeap::from_vec(vec![(4, 0), (2, 1)]);
+        assert_eq!(heap.pop_min(), Some((2, 1)));
+        heap.clear();
+        assert!(heap.is_empty());
+        assert_eq!(heap.pop_min(), None);
+    }
+
+    #[test]
+    fn custom_order() {
+        // Even keys first, each parity from small to large
+        let order = FnOrder(|a: &i32, b: &i32| (a % 2, a) < (b % 2, b));
+        let heap = Heap::from_vec_with_order(KEYS.iter().map(|k| (*k, 0)).collect(), order);
+        assert_eq!(heap.iter().count(), 8);
+        assert_eq!(keys(heap), vec![2, 8, 1, 3, 3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn ord_f64_total_order() {
+        let values = [
+            1.0,
+            f64::NAN,
+            -f64::INFINITY,
+            -0.0,
+            0.0,
+            -f64::NAN,
+            f64::INFINITY,
+        ];
+        let mut heap: MaxHeap<OrdF64, usize> = MaxHeap::new();
+        heap.extend(values.iter().map(|v| (OrdF64(*v), 0)));
+        let popped = heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(k, _)| k.0)
+            .collect::<Vec<_>>();
+        assert!(popped[0].is_nan() && popped[0].is_sign_positive());
+        assert_eq!(popped[1..5], [f64::INFINITY, 1.0, 0.0, -0.0]);
+        assert!(popped[4].is_sign_negative());
+        assert_eq!(popped[5], -f64::INFINITY);
+        assert!(popped[6].is_nan() && popped[6].is_sign_negative());
+        assert_eq!(OrdF64(f64::NAN), OrdF64(f64::NAN));
+        assert!(OrdF64(0.0) > OrdF64(-0.0));
+    }
 }
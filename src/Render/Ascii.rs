@@ -0,0 +1,184 @@
+use crate::BinaryTree::BinaryTree::{BinaryT, NodeIndex};
+use std::collections::HashMap;
+
+/// Options of the text renderer
+pub(crate) struct AsciiOptions {
+    /// The maximum number of columns including the labels
+    pub(crate) width: usize,
+    /// Drawing a height axis below the tree
+    pub(crate) axis: bool,
+}
+
+/// The narrowest plot of the tree, labels are cut if there is no room
+const MIN_PLOT_WIDTH: usize = 10;
+
+/// Picking the box-drawing character of a junction from the
+/// directions the lines leave it
+fn junction(up: bool, down: bool, left: bool, right: bool) -> char {
+    match (up, down, left, right) {
+        (false, true, false, true) => '┌',
+        (true, false, false, true) => '└',
+        (true, true, false, false) => '│',
+        (true, true, false, true) => '├',
+        (true, true, true, false) => '┤',
+        (true, true, true, true) => '┼',
+        (false, true, true, true) => '┬',
+        (true, false, true, true) => '┴',
+        (false, true, true, false) => '┐',
+        (true, false, true, false) => '┘',
+        (false, false, _, _) => '─',
+        (_, _, _, _) => '│',
+    }
+}
+
+/// Cutting a label to at most `width` characters, marking the cut with `…`
+fn cut_label(label: &str, width: usize) -> String {
+    if label.chars().count() <= width {
+        return label.to_string();
+    }
+    let mut out = label
+        .chars()
+        .take(width.saturating_sub(1))
+        .collect::<String>();
+    out.push('…');
+    out
+}
+
+/// Drawing the tree as a horizontal dendrogram, the root is on the
+/// left and each leaf takes one line with it's label on the right
+pub(crate) fn to_ascii(tree: &BinaryT, options: &AsciiOptions) -> String {
+    let root = tree
+        .root_index
+        .expect("root node is none, which means the clustering is not done");
+
+    // Pre-order, so walking it backwards visits children before parents
+    let nodes = tree
+        .pre_order_from(root)
+        .map(|v| v.index)
+        .collect::<Vec<_>>();
+    let leafs = nodes
+        .iter()
+        .filter_map(|idx| tree.label(*idx))
+        .collect::<Vec<_>>();
+    // The number of ancestors of each node, a node is drawn at least
+    // this many columns from the left so it's right of it's parent
+    let mut depths: HashMap<NodeIndex, usize> = HashMap::new();
+    for idx in nodes.iter() {
+        let depth = tree.parent(*idx).map_or(0, |p| depths[&p] + 1);
+        depths.insert(*idx, depth);
+    }
+    // A line is the `plot_width + 1` columns of the plot,
+    // a space and the label
+    let label_width = leafs.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let plot_width = options
+        .width
+        .saturating_sub(label_width + 2)
+        .max(MIN_PLOT_WIDTH)
+        .max(depths.values().copied().max().unwrap_or(0));
+    let label_room = options.width.saturating_sub(plot_width + 2).max(1);
+
+    let root_height = tree.height(root);
+    let scale = if root_height > 0.0 {
+        plot_width as f64 / root_height
+    } else {
+        0.0
+    };
+    let mut rows: HashMap<NodeIndex, usize> = HashMap::new();
+    let mut cols: HashMap<NodeIndex, usize> = HashMap::new();
+    let mut next_row = 0;
+    for idx in nodes.iter() {
+        if tree.label(*idx).is_some() {
+            rows.insert(*idx, next_row);
+            cols.insert(*idx, plot_width);
+            next_row += 1;
+        }
+    }
+    for idx in nodes.iter().rev() {
+        let children = tree.children(*idx);
+        if children.is_empty() {
+            continue;
+        }
+        let top = children.iter().map(|c| rows[c]).min().unwrap();
+        let bottom = children.iter().map(|c| rows[c]).max().unwrap();
+        rows.insert(*idx, (top + bottom) / 2);
+        let scaled = ((root_height - tree.height(*idx)) * scale).round() as usize;
+        let scaled = scaled.max(depths[idx]);
+        // Keeping at least one column between a node and it's children
+        let room = children.iter().map(|c| cols[c]).min().unwrap();
+        cols.insert(*idx, scaled.min(room.saturating_sub(1)));
+    }
+
+    let mut grid = vec![vec![' '; plot_width + 1]; leafs.len()];
+    for idx in nodes.iter() {
+        let (row, col) = (rows[idx], cols[idx]);
+        if let Some(p) = tree.parent(*idx) {
+            grid[row][(cols[&p] + 1)..col]
+                .iter_mut()
+                .for_each(|c| *c = '─');
+        }
+        let children = tree.children(*idx);
+        if children.is_empty() {
+            grid[row][col] = '─';
+            continue;
+        }
+        let child_rows = children.iter().map(|c| rows[c]).collect::<Vec<_>>();
+        let top = *child_rows.iter().min().unwrap();
+        let bottom = *child_rows.iter().max().unwrap();
+        for (r, line) in grid.iter_mut().enumerate().take(bottom + 1).skip(top) {
+            line[col] = junction(r > top, r < bottom, r == row, child_rows.contains(&r));
+        }
+    }
+
+    let mut out = String::new();
+    for (line, label) in grid.iter().zip(leafs.iter()) {
+        out.extend(line.iter());
+        out.push(' ');
+        out.push_str(&cut_label(label, label_room));
+        out.push('\n');
+    }
+    if options.axis {
+        out.push_str(&axis(root_height, plot_width));
+    }
+    out
+}
+
+/// An axis of heights under the plot, the root is on the left end
+fn axis(root_height: f64, plot_width: usize) -> String {
+    let ticks = 4;
+    let mut line = vec!['─'; plot_width + 1];
+    let mut labels = vec![' '; plot_width + 1];
+    for t in 0..=ticks {
+        let col = plot_width * t / ticks;
+        line[col] = if t == 0 {
+            '└'
+        } else if t == ticks {
+            '┘'
+        } else {
+            '┴'
+        };
+        let height = root_height * (1.0 - t as f64 / ticks as f64);
+        let text = format!("{:.3}", height);
+        let text = text.trim_end_matches('0').trim_end_matches('.');
+        // Right aligned to the last tick and left aligned to the others
+        let start = if t == ticks {
+            (col + 1).saturating_sub(text.len())
+        } else {
+            col
+        };
+        let free = labels[start.saturating_sub(1)..(start + text.len()).min(labels.len())]
+            .iter()
+            .all(|c| *c == ' ');
+        if free {
+            for (i, ch) in text.chars().enumerate() {
+                if start + i < labels.len() {
+                    labels[start + i] = ch;
+                }
+            }
+        }
+    }
+    let mut out = line.into_iter().collect::<String>();
+    out.push('\n');
+    out.push_str(labels.into_iter().collect::<String>().trim_end());
+    out.push('\n');
+    out
+}
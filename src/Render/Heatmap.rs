@@ -0,0 +1,199 @@
+use super::Svg::{branch_path, places, Layout};
+use crate::BinaryTree::BinaryTree::{dense_matrix, ArcStr, BinaryT};
+use crate::TreeWriter::Xml::escape;
+use ABtree::BTree;
+
+/// Options of the heatmap renderer
+pub(crate) struct HeatmapOptions {
+    /// The side of a cell in pixels
+    pub(crate) cell: f64,
+    /// The room for the dendrograms on the top and on the left in pixels
+    pub(crate) dendrogram: f64,
+    pub(crate) font_size: f64,
+}
+
+const MARGIN: f64 = 20.0;
+
+/// The colours of the lowest and highest values
+const LOW: (f64, f64, f64) = (247.0, 251.0, 255.0);
+const HIGH: (f64, f64, f64) = (8.0, 48.0, 107.0);
+
+/// The matrix reordered by the leaf order of the tree, the diagonal
+/// is the self value of the tree, and the range of the values
+fn ordered_matrix<'content>(
+    tree: &BinaryT<'content>,
+    pair_value_dict: &BTree<ArcStr<'content>, BTree<ArcStr<'content>, f64>>,
+) -> (Vec<&'content str>, Vec<Vec<Option<f64>>>, f64, f64) {
+    let labels = tree.leaf_labels();
+    let mut matrix = dense_matrix(pair_value_dict, &labels);
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[i] = Some(tree.self_value());
+    }
+    let values = matrix.iter().flatten().flatten();
+    let low = values.clone().cloned().fold(f64::MAX, f64::min);
+    let high = values.cloned().fold(f64::MIN, f64::max);
+    (labels, matrix, low, high)
+}
+
+/// Scaling a value into [0, 1] where 1 is the highest value
+fn scale(value: f64, low: f64, high: f64) -> f64 {
+    if high > low {
+        (value - low) / (high - low)
+    } else {
+        1.0
+    }
+}
+
+fn colour(v: f64) -> String {
+    let mix = |a: f64, b: f64| (a + (b - a) * v).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        mix(LOW.0, HIGH.0),
+        mix(LOW.1, HIGH.1),
+        mix(LOW.2, HIGH.2)
+    )
+}
+
+/// Drawing the paired values reordered by the leaf order of the tree as
+/// an SVG heatmap, with the dendrogram on the top and on the left margin.
+/// Higher values are darker and missing pairs are grey
+pub(crate) fn to_heatmap_svg<'content>(
+    tree: &BinaryT<'content>,
+    pair_value_dict: &BTree<ArcStr<'content>, BTree<ArcStr<'content>, f64>>,
+    options: &HeatmapOptions,
+) -> String {
+    let root = tree
+        .root_index
+        .expect("root node is none, which means the clustering is not done");
+    let (labels, matrix, low, high) = ordered_matrix(tree, pair_value_dict);
+    let n = labels.len() as f64;
+    let cell = options.cell;
+    let font = options.font_size;
+    let label_space =
+        labels.iter().map(|l| l.chars().count()).max().unwrap_or(0) as f64 * font * 0.6 + 8.0;
+    let ox = MARGIN + options.dendrogram;
+    let oy = MARGIN + options.dendrogram;
+    let width = ox + n * cell + label_space + MARGIN;
+    let bar_y = oy + n * cell + label_space;
+    let height = bar_y + 2.0 * font + MARGIN;
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.0} {:.0}\" font-family=\"sans-serif\" font-size=\"{}\">\n",
+        width, height, width, height, font
+    );
+    out.push_str(&format!(
+        "<rect width=\"{:.0}\" height=\"{:.0}\" fill=\"white\"/>\n",
+        width, height
+    ));
+    for (i, row) in matrix.iter().enumerate() {
+        for (j, v) in row.iter().enumerate() {
+            let fill = match v {
+                Some(v) => colour(scale(*v, low, high)),
+                None => "#bdbdbd".to_string(),
+            };
+            out.push_str(&format!(
+                "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"{}\"/>\n",
+                ox + j as f64 * cell,
+                oy + i as f64 * cell,
+                cell,
+                cell,
+                fill
+            ));
+        }
+    }
+    for (i, l) in labels.iter().enumerate() {
+        let c = (i as f64 + 0.5) * cell;
+        out.push_str(&format!(
+            "<text x=\"{:.2}\" y=\"{:.2}\" dominant-baseline=\"middle\">{}</text>\n",
+            ox + n * cell + 4.0,
+            oy + c,
+            escape(l)
+        ));
+        out.push_str(&format!(
+            "<text transform=\"translate({:.2},{:.2}) rotate(90)\" dominant-baseline=\"middle\">{}</text>\n",
+            ox + c,
+            oy + n * cell + 4.0,
+            escape(l)
+        ));
+    }
+
+    // The dendrograms on the margins
+    let nodes = tree
+        .pre_order_from(root)
+        .map(|v| v.index)
+        .collect::<Vec<_>>();
+    let size = options.dendrogram - 4.0;
+    let place = places(tree, &nodes, size);
+    let left = |t: f64, d: f64| (MARGIN + d, oy + (t + 0.5) * cell);
+    let top = |t: f64, d: f64| (ox + (t + 0.5) * cell, MARGIN + d);
+    let mut path = branch_path(tree, &nodes, &place, Layout::Horizontal, &left, 0.0);
+    path.push_str(&branch_path(
+        tree,
+        &nodes,
+        &place,
+        Layout::Vertical,
+        &top,
+        0.0,
+    ));
+    out.push_str(&format!(
+        "<path d=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"1\"/>\n",
+        path.trim_end()
+    ));
+
+    // The colour bar from the lowest to the highest value
+    let steps = 20;
+    let bar_w = (n * cell).max(100.0) / steps as f64;
+    for s in 0..steps {
+        out.push_str(&format!(
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"{}\"/>\n",
+            ox + s as f64 * bar_w,
+            bar_y,
+            bar_w,
+            font,
+            colour(s as f64 / (steps - 1) as f64)
+        ));
+    }
+    out.push_str(&format!(
+        "<text x=\"{:.2}\" y=\"{:.2}\" text-anchor=\"end\" dominant-baseline=\"middle\">{}</text>\n",
+        ox - 4.0,
+        bar_y + font / 2.0,
+        low
+    ));
+    out.push_str(&format!(
+        "<text x=\"{:.2}\" y=\"{:.2}\" dominant-baseline=\"middle\">{}</text>\n",
+        ox + steps as f64 * bar_w + 4.0,
+        bar_y + font / 2.0,
+        high
+    ));
+    out.push_str("</svg>\n");
+    out
+}
+
+/// The same reordered matrix as a plain PGM (P2) picture without the
+/// dendrograms, each pair takes `cell` x `cell` pixels.
+/// Higher values are darker, from 230 down to 0, and missing pairs are white
+pub(crate) fn to_heatmap_pgm<'content>(
+    tree: &BinaryT<'content>,
+    pair_value_dict: &BTree<ArcStr<'content>, BTree<ArcStr<'content>, f64>>,
+    cell: usize,
+) -> String {
+    let (labels, matrix, low, high) = ordered_matrix(tree, pair_value_dict);
+    let side = labels.len() * cell.max(1);
+    let mut out = format!("P2\n# {}\n{} {}\n255\n", labels.join(" "), side, side);
+    for row in matrix.iter() {
+        let line = row
+            .iter()
+            .map(|v| match v {
+                Some(v) => ((1.0 - scale(*v, low, high)) * 230.0).round() as u8,
+                None => 255,
+            })
+            .flat_map(|g| std::iter::repeat_n(g.to_string(), cell.max(1)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        for _ in 0..cell.max(1) {
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -0,0 +1,398 @@
+use crate::BinaryTree::BinaryTree::{BinaryT, NodeIndex};
+use crate::TreeWriter::Metadata::LeafMetadata;
+use crate::TreeWriter::Xml::escape;
+use clap::ArgEnum;
+use std::collections::{BTreeSet, HashMap};
+use std::f64::consts::PI;
+
+/// The placement of the tree in the picture
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Layout {
+    /// The root on the left and the leafs on the right
+    Horizontal,
+    /// The root on the top and the leafs at the bottom
+    Vertical,
+    /// The root in the center and the leafs on a circle
+    Circular,
+}
+
+/// Options of the SVG renderer
+pub(crate) struct SvgOptions<'m> {
+    pub(crate) layout: Layout,
+    pub(crate) font_size: f64,
+    /// The length in pixels from the root to the leafs
+    pub(crate) size: f64,
+    /// Colouring the leafs by a column of the metadata, a value
+    /// starting with `#` is used as the colour directly
+    pub(crate) colour_by: Option<(&'m LeafMetadata, &'m str)>,
+    /// The roots of the flat clusters to highlight
+    pub(crate) clusters: Vec<NodeIndex>,
+}
+
+/// The colours used for categories and clusters
+pub(crate) const PALETTE: [&str; 10] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f", "#bab0ac",
+];
+
+const MARGIN: f64 = 20.0;
+
+/// The position of a node, `t` is the position along the leafs where the
+/// i-th leaf is at `i` and `d` is the distance from the root in pixels
+pub(crate) struct Place {
+    pub(crate) t: f64,
+    pub(crate) d: f64,
+    pub(crate) first_leaf: usize,
+    pub(crate) last_leaf: usize,
+}
+
+/// Rounding a length to 1, 2 or 5 times a power of ten
+pub(crate) fn nice_length(x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let base = 10f64.powf(x.log10().floor());
+    [5.0, 2.0, 1.0]
+        .iter()
+        .map(|m| m * base)
+        .find(|v| *v <= x)
+        .unwrap_or(base)
+}
+
+/// Computing the place of each node, `size` is the distance
+/// from the root to the leafs in pixels
+pub(crate) fn places(tree: &BinaryT, nodes: &[NodeIndex], size: f64) -> HashMap<NodeIndex, Place> {
+    let root_height = tree.height(nodes[0]);
+    let scale = if root_height > 0.0 {
+        size / root_height
+    } else {
+        0.0
+    };
+    let mut out: HashMap<NodeIndex, Place> = HashMap::new();
+    let mut next_leaf = 0;
+    for idx in nodes.iter() {
+        if tree.label(*idx).is_some() {
+            out.insert(
+                *idx,
+                Place {
+                    t: next_leaf as f64,
+                    d: size,
+                    first_leaf: next_leaf,
+                    last_leaf: next_leaf,
+                },
+            );
+            next_leaf += 1;
+        }
+    }
+    for idx in nodes.iter().rev() {
+        let children = tree.children(*idx);
+        if children.is_empty() {
+            continue;
+        }
+        let ts = children.iter().map(|c| out[c].t).collect::<Vec<_>>();
+        let t = (ts.iter().cloned().fold(f64::MAX, f64::min)
+            + ts.iter().cloned().fold(f64::MIN, f64::max))
+            / 2.0;
+        let first_leaf = children.iter().map(|c| out[c].first_leaf).min().unwrap();
+        let last_leaf = children.iter().map(|c| out[c].last_leaf).max().unwrap();
+        let d = (root_height - tree.height(*idx)) * scale;
+        out.insert(
+            *idx,
+            Place {
+                t,
+                d,
+                first_leaf,
+                last_leaf,
+            },
+        );
+    }
+    out
+}
+
+/// The path data of the branches, each inner node is joined to it's
+/// children by a line along the leafs at it's own height.
+/// `point` maps `(t, d)` into the picture and `radius_t` is the angle
+/// between two leafs of the circular layout
+pub(crate) fn branch_path<F: Fn(f64, f64) -> (f64, f64)>(
+    tree: &BinaryT,
+    nodes: &[NodeIndex],
+    place: &HashMap<NodeIndex, Place>,
+    layout: Layout,
+    point: &F,
+    radius_t: f64,
+) -> String {
+    let mut path = String::new();
+    for idx in nodes.iter() {
+        let children = tree.children(*idx);
+        if children.is_empty() {
+            continue;
+        }
+        let p = &place[idx];
+        let t_min = children.iter().map(|c| place[c].t).fold(f64::MAX, f64::min);
+        let t_max = children.iter().map(|c| place[c].t).fold(f64::MIN, f64::max);
+        let (x0, y0) = point(t_min, p.d);
+        let (x1, y1) = point(t_max, p.d);
+        match layout {
+            Layout::Circular => {
+                let large = if (t_max - t_min) * radius_t > PI {
+                    1
+                } else {
+                    0
+                };
+                path.push_str(&format!(
+                    "M{:.2},{:.2} A{:.2},{:.2} 0 {} 1 {:.2},{:.2} ",
+                    x0, y0, p.d, p.d, large, x1, y1
+                ));
+            }
+            _ => path.push_str(&format!("M{:.2},{:.2} L{:.2},{:.2} ", x0, y0, x1, y1)),
+        }
+        for c in children.iter() {
+            let (x0, y0) = point(place[c].t, p.d);
+            let (x1, y1) = point(place[c].t, place[c].d);
+            path.push_str(&format!("M{:.2},{:.2} L{:.2},{:.2} ", x0, y0, x1, y1));
+        }
+    }
+    path
+}
+
+/// The colour of each leaf and the legend of the categories
+fn leaf_colours(
+    tree: &BinaryT,
+    nodes: &[NodeIndex],
+    options: &SvgOptions,
+) -> (HashMap<NodeIndex, String>, Vec<(String, String)>) {
+    let (metadata, key) = match options.colour_by {
+        None => return (HashMap::new(), vec![]),
+        Some(o) => o,
+    };
+    let values = nodes
+        .iter()
+        .filter_map(|idx| tree.label(*idx).map(|l| (*idx, metadata.get(l, key))))
+        .filter_map(|(idx, v)| v.map(|v| (idx, v)))
+        .collect::<Vec<_>>();
+    let categories = values
+        .iter()
+        .map(|(_, v)| *v)
+        .filter(|v| !v.starts_with('#'))
+        .collect::<BTreeSet<_>>();
+    let category_colour = categories
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (*c, PALETTE[i % PALETTE.len()]))
+        .collect::<HashMap<_, _>>();
+    let colours = values
+        .iter()
+        .map(|(idx, v)| {
+            let c = category_colour.get(v).copied().unwrap_or(v);
+            (*idx, c.to_string())
+        })
+        .collect();
+    let legend = categories
+        .iter()
+        .map(|c| (c.to_string(), category_colour[c].to_string()))
+        .collect();
+    (colours, legend)
+}
+
+/// Drawing the tree as an SVG picture with a scale bar
+pub(crate) fn to_svg(tree: &BinaryT, options: &SvgOptions) -> String {
+    let root = tree
+        .root_index
+        .expect("root node is none, which means the clustering is not done");
+    let nodes = tree
+        .pre_order_from(root)
+        .map(|v| v.index)
+        .collect::<Vec<_>>();
+    let leaf_nums = nodes.iter().filter(|i| tree.label(**i).is_some()).count();
+    let font = options.font_size;
+    let step = font * 1.4;
+    let label_chars = nodes
+        .iter()
+        .filter_map(|i| tree.label(*i))
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0);
+    let label_space = label_chars as f64 * font * 0.6 + 8.0;
+    let size = options.size;
+    let place = places(tree, &nodes, size);
+    let (colours, legend) = leaf_colours(tree, &nodes, options);
+
+    // Mapping (t, d) into the picture
+    let radius_t = 2.0 * PI / leaf_nums.max(1) as f64;
+    let center = MARGIN + label_space + size;
+    let point = |t: f64, d: f64| -> (f64, f64) {
+        match options.layout {
+            Layout::Horizontal => (MARGIN + d, MARGIN + (t + 0.5) * step),
+            Layout::Vertical => (MARGIN + (t + 0.5) * step, MARGIN + d),
+            Layout::Circular => {
+                let a = t * radius_t;
+                (center + d * a.cos(), center + d * a.sin())
+            }
+        }
+    };
+    let (plot_w, plot_h) = match options.layout {
+        Layout::Horizontal => (
+            2.0 * MARGIN + size + label_space,
+            2.0 * MARGIN + leaf_nums as f64 * step,
+        ),
+        Layout::Vertical => (
+            2.0 * MARGIN + leaf_nums as f64 * step,
+            2.0 * MARGIN + size + label_space,
+        ),
+        Layout::Circular => (2.0 * center, 2.0 * center),
+    };
+    let footer = 2.0 * step + legend.len() as f64 * step;
+    let width = plot_w.max(2.0 * MARGIN + 200.0);
+    let height = plot_h + footer;
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.0} {:.0}\" font-family=\"sans-serif\" font-size=\"{}\">\n",
+        width, height, width, height, font
+    );
+    out.push_str(&format!(
+        "<rect width=\"{:.0}\" height=\"{:.0}\" fill=\"white\"/>\n",
+        width, height
+    ));
+
+    // Highlighting the flat clusters behind the tree
+    for (i, c) in options.clusters.iter().enumerate() {
+        let p = &place[c];
+        let colour = PALETTE[i % PALETTE.len()];
+        let (t0, t1) = (p.first_leaf as f64 - 0.5, p.last_leaf as f64 + 0.5);
+        let shape = match options.layout {
+            Layout::Horizontal | Layout::Vertical => {
+                let (x0, y0) = point(t0, p.d - 4.0);
+                let (x1, y1) = point(t1, size + label_space);
+                format!(
+                    "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\"",
+                    x0.min(x1),
+                    y0.min(y1),
+                    (x1 - x0).abs(),
+                    (y1 - y0).abs()
+                )
+            }
+            Layout::Circular => {
+                let (r0, r1) = ((p.d - 4.0).max(0.0), size + label_space);
+                let large = if (t1 - t0) * radius_t > PI { 1 } else { 0 };
+                let (a0, b0) = point(t0, r0);
+                let (a1, b1) = point(t1, r0);
+                let (c1, d1) = point(t1, r1);
+                let (c0, d0) = point(t0, r1);
+                format!(
+                    "<path d=\"M{:.2},{:.2} A{:.2},{:.2} 0 {} 1 {:.2},{:.2} L{:.2},{:.2} A{:.2},{:.2} 0 {} 0 {:.2},{:.2} Z\"",
+                    a0, b0, r0, r0, large, a1, b1, c1, d1, r1, r1, large, c0, d0
+                )
+            }
+        };
+        out.push_str(&format!(
+            "{} fill=\"{}\" fill-opacity=\"0.2\"/>\n",
+            shape, colour
+        ));
+    }
+
+    // The branches
+    let mut path = branch_path(tree, &nodes, &place, options.layout, &point, radius_t);
+    // A short stem above the root
+    let (x, y) = point(place[&root].t, 0.0);
+    let (x0, y0) = match options.layout {
+        Layout::Horizontal => (x - 8.0, y),
+        Layout::Vertical => (x, y - 8.0),
+        Layout::Circular => (x, y),
+    };
+    path.push_str(&format!("M{:.2},{:.2} L{:.2},{:.2}", x0, y0, x, y));
+    out.push_str(&format!(
+        "<path d=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"1\"/>\n",
+        path.trim_end()
+    ));
+
+    // The leaf labels
+    for idx in nodes.iter() {
+        let label = match tree.label(*idx) {
+            Some(l) => l,
+            None => continue,
+        };
+        let p = &place[idx];
+        let (x, y) = point(p.t, p.d + 4.0);
+        let fill = colours
+            .get(idx)
+            .map(|c| format!(" fill=\"{}\"", escape(c)))
+            .unwrap_or_default();
+        if colours.contains_key(idx) {
+            let (cx, cy) = point(p.t, p.d);
+            out.push_str(&format!(
+                "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\"{}/>\n",
+                cx,
+                cy,
+                font / 4.0,
+                fill
+            ));
+        }
+        let (anchor, transform) = match options.layout {
+            Layout::Horizontal => ("start", format!("translate({:.2},{:.2})", x, y)),
+            Layout::Vertical => ("start", format!("translate({:.2},{:.2}) rotate(90)", x, y)),
+            Layout::Circular => {
+                let degree = p.t * radius_t * 180.0 / PI;
+                if degree > 90.0 && degree < 270.0 {
+                    (
+                        "end",
+                        format!("translate({:.2},{:.2}) rotate({:.2})", x, y, degree - 180.0),
+                    )
+                } else {
+                    (
+                        "start",
+                        format!("translate({:.2},{:.2}) rotate({:.2})", x, y, degree),
+                    )
+                }
+            }
+        };
+        out.push_str(&format!(
+            "<text transform=\"{}\" text-anchor=\"{}\" dominant-baseline=\"middle\"{}>{}</text>\n",
+            transform,
+            anchor,
+            fill,
+            escape(label)
+        ));
+    }
+
+    // The scale bar under the picture
+    let root_height = tree.height(root);
+    let bar_value = nice_length(root_height / 4.0);
+    if root_height > 0.0 && bar_value > 0.0 {
+        let bar = bar_value / root_height * size;
+        let y = plot_h + step / 2.0;
+        out.push_str(&format!(
+            "<path d=\"M{:.2},{:.2} L{:.2},{:.2}\" stroke=\"black\" stroke-width=\"1\"/>\n",
+            MARGIN,
+            y,
+            MARGIN + bar,
+            y
+        ));
+        out.push_str(&format!(
+            "<text x=\"{:.2}\" y=\"{:.2}\" text-anchor=\"start\" dominant-baseline=\"middle\">{}</text>\n",
+            MARGIN + bar + 4.0,
+            y,
+            bar_value
+        ));
+    }
+
+    // The legend of the leaf colours
+    for (i, (name, colour)) in legend.iter().enumerate() {
+        let y = plot_h + (2.0 + i as f64) * step;
+        out.push_str(&format!(
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"{}\"/>\n",
+            MARGIN,
+            y - font / 2.0,
+            font,
+            font,
+            colour
+        ));
+        out.push_str(&format!(
+            "<text x=\"{:.2}\" y=\"{:.2}\" dominant-baseline=\"middle\">{}</text>\n",
+            MARGIN + font + 4.0,
+            y,
+            escape(name)
+        ));
+    }
+    out.push_str("</svg>\n");
+    out
+}
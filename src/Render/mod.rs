@@ -0,0 +1,3 @@
+pub(crate) mod Ascii;
+pub(crate) mod Heatmap;
+pub(crate) mod Svg;
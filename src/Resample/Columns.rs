@@ -0,0 +1,150 @@
+/// The values of the samples, one row per sample
+enum Values {
+    /// Numbers compared by the Euclidean distance
+    Features(Vec<Vec<f64>>),
+    /// Aligned characters compared by the proportion of differing sites
+    Sequences(Vec<Vec<u8>>),
+}
+
+/// Samples described by the same columns, which
+/// are resampled to measure the support of the clades
+pub(crate) struct Columns<'content> {
+    pub(crate) labels: Vec<&'content str>,
+    values: Values,
+}
+
+/// A gap or an unknown character, sites having one of them
+/// in either sequence are left out of the distance
+#[inline]
+fn is_gap(c: u8) -> bool {
+    matches!(c, b'-' | b'.' | b'?')
+}
+
+impl<'content> Columns<'content> {
+    /// Loading tab separated features, the first line is the
+    /// header and the first column is the sample label
+    pub(crate) fn from_tsv(content: &'content str) -> Self {
+        let mut lines = content
+            .lines()
+            .map(|l| l.trim_end_matches('\r'))
+            .filter(|l| !l.is_empty());
+        let header = lines.next().expect("The feature file is empty");
+        let width = header.split('\t').count() - 1;
+        let mut labels = Vec::new();
+        let mut rows = Vec::new();
+        for line in lines {
+            let mut cols = line.split('\t');
+            labels.push(cols.next().unwrap());
+            let row = cols
+                .map(|c| {
+                    c.trim()
+                        .parse::<f64>()
+                        .unwrap_or_else(|_| panic!("Can not parse the value of line: {}", line))
+                })
+                .collect::<Vec<_>>();
+            if row.len() != width {
+                panic!("Wrong format for the line of features: {}", line);
+            }
+            rows.push(row);
+        }
+        Columns {
+            labels,
+            values: Values::Features(rows),
+        }
+    }
+
+    /// Loading aligned sequences in FASTA format, the label of a
+    /// sequence is the first word of it's header line
+    pub(crate) fn from_fasta(content: &'content str) -> Self {
+        let mut labels = Vec::new();
+        let mut rows: Vec<Vec<u8>> = Vec::new();
+        for line in content.lines().map(|l| l.trim_end_matches('\r')) {
+            if let Some(header) = line.strip_prefix('>') {
+                let label = header
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_else(|| panic!("Empty header line in the alignment"));
+                labels.push(label);
+                rows.push(Vec::new());
+            } else if let Some(row) = rows.last_mut() {
+                row.extend(line.trim().bytes().map(|c| c.to_ascii_uppercase()));
+            } else if !line.trim().is_empty() {
+                panic!("The alignment must start with a header line");
+            }
+        }
+        if let Some(first) = rows.first() {
+            if let Some(i) = rows.iter().position(|r| r.len() != first.len()) {
+                panic!(
+                    "The sequence of {} has {} sites but the sequence of {} has {}",
+                    labels[i],
+                    rows[i].len(),
+                    labels[0],
+                    first.len()
+                );
+            }
+        }
+        Columns {
+            labels,
+            values: Values::Sequences(rows),
+        }
+    }
+
+    /// The number of columns
+    pub(crate) fn width(&self) -> usize {
+        match &self.values {
+            Values::Features(rows) => rows.first().map_or(0, |r| r.len()),
+            Values::Sequences(rows) => rows.first().map_or(0, |r| r.len()),
+        }
+    }
+
+    /// The distance of two samples where column `c` counts `weights[c]`
+    /// times, `None` if no column can be compared
+    fn distance(&self, a: usize, b: usize, weights: &[usize]) -> Option<f64> {
+        match &self.values {
+            Values::Features(rows) => {
+                let sum = rows[a]
+                    .iter()
+                    .zip(rows[b].iter())
+                    .zip(weights.iter())
+                    .map(|((x, y), w)| (x - y) * (x - y) * *w as f64)
+                    .sum::<f64>();
+                Some(sum.sqrt())
+            }
+            Values::Sequences(rows) => {
+                let mut sites = 0;
+                let mut differing = 0;
+                for ((x, y), w) in rows[a].iter().zip(rows[b].iter()).zip(weights.iter()) {
+                    if is_gap(*x) || is_gap(*y) {
+                        continue;
+                    }
+                    sites += w;
+                    if x != y {
+                        differing += w;
+                    }
+                }
+                if sites == 0 {
+                    None
+                } else {
+                    Some(differing as f64 / sites as f64)
+                }
+            }
+        }
+    }
+
+    /// The rows of `(from, to, value)` for each pair of samples, the value
+    /// is the negative distance so that a larger value is more similar
+    pub(crate) fn rows(
+        &self,
+        weights: &[usize],
+    ) -> Vec<(&'content str, &'content str, Option<f64>)> {
+        let n = self.labels.len();
+        let mut rows = Vec::with_capacity(n * n.saturating_sub(1) / 2);
+        for a in 0..n {
+            for b in (a + 1)..n {
+                let value = self.distance(a, b, weights).map(|d| -d);
+                rows.push((self.labels[a], self.labels[b], value));
+            }
+        }
+        rows
+    }
+}
@@ -0,0 +1,46 @@
+/// The SplitMix64 generator, small and good enough
+/// for drawing columns with a fixed seed
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        SplitMix64(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+}
+
+/// How many times each of `width` columns is drawn
+/// when drawing `width` columns with replacement
+pub(crate) fn bootstrap_weights(rng: &mut SplitMix64, width: usize) -> Vec<usize> {
+    let mut weights = vec![0; width];
+    for _ in 0..width {
+        weights[rng.below(width)] += 1;
+    }
+    weights
+}
+
+/// Keeping `keep` of `width` columns drawn without replacement,
+/// a kept column has the weight 1 and the others 0
+pub(crate) fn jackknife_weights(rng: &mut SplitMix64, width: usize, keep: usize) -> Vec<usize> {
+    let mut columns = (0..width).collect::<Vec<_>>();
+    let mut weights = vec![0; width];
+    // A partial Fisher-Yates shuffle
+    for i in 0..keep.min(width) {
+        let j = i + rng.below(width - i);
+        columns.swap(i, j);
+        weights[columns[i]] = 1;
+    }
+    weights
+}
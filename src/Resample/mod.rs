@@ -0,0 +1,2 @@
+pub(crate) mod Columns;
+pub(crate) mod Random;
@@ -0,0 +1,45 @@
+use super::Json::quote;
+use crate::BinaryTree::BinaryTree::BinaryT;
+
+/// Generating a Graphviz digraph where each node of the tree is a vertex
+/// and each edge goes from the parent to the child.
+/// `height` and `size` are reserved by Graphviz for the drawing, so
+/// the node values are written as `node_height` and `node_size`, and
+/// the branch length as `branch_length`. Only neato draws the edges
+/// with their `len`, the other layouts ignore it, so the branch length
+/// is shown as the edge `label` too.
+/// A clade support is shown as the `xlabel` of it's node
+pub(crate) fn to_dot(tree: &BinaryT) -> String {
+    let root = tree
+        .root_index
+        .expect("root node is none, which means the clustering is not done");
+    let mut out = String::from("digraph tree {\n\trankdir=LR;\n\tnode [shape=point];\n");
+    let mut edges = String::new();
+    for v in tree.pre_order_from(root) {
+        let idx = v.index;
+        let mut attrs = match tree.label(idx) {
+            Some(l) => format!("shape=plaintext, label={}", quote(l)),
+            None => "label=\"\"".to_string(),
+        };
+        if let Some(s) = tree.support(idx) {
+            attrs.push_str(&format!(", xlabel=\"{}\", support={}", s, s));
+        }
+        out.push_str(&format!(
+            "\tn{} [{}, node_height={}, node_size={}];\n",
+            idx,
+            attrs,
+            tree.height(idx),
+            tree.size(idx)
+        ));
+        for c in v.children {
+            let b = tree.branch_length(c).unwrap_or(0.0);
+            edges.push_str(&format!(
+                "\tn{} -> n{} [len={}, branch_length={}, label=\"{}\"];\n",
+                idx, c, b, b, b
+            ));
+        }
+    }
+    out.push_str(&edges);
+    out.push_str("}\n");
+    out
+}
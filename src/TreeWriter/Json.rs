@@ -0,0 +1,101 @@
+use crate::BinaryTree::BinaryTree::{BinaryT, NodeIndex};
+
+/// Writing a str as a JSON string literal
+pub(crate) fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The name of a node, leafs have their label and inner nodes are `null`
+fn name(tree: &BinaryT, index: NodeIndex) -> String {
+    tree.label(index)
+        .map(quote)
+        .unwrap_or_else(|| "null".to_string())
+}
+
+/// The `,"support":..` member of a node with a support, else empty
+fn support(tree: &BinaryT, index: NodeIndex) -> String {
+    tree.support(index)
+        .map(|s| format!(",\"support\":{}", s))
+        .unwrap_or_default()
+}
+
+fn write_node(tree: &BinaryT, index: NodeIndex, out: &mut String) {
+    out.push_str(&format!(
+        "{{\"name\":{},\"height\":{},\"size\":{}{}",
+        name(tree, index),
+        tree.height(index),
+        tree.size(index),
+        support(tree, index)
+    ));
+    let children = tree.children(index);
+    if !children.is_empty() {
+        out.push_str(",\"children\":[");
+        for (i, c) in children.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_node(tree, c, out);
+        }
+        out.push(']');
+    }
+    out.push('}');
+}
+
+/// Generating nested `{name, height, size, support, children}` objects
+/// which can be passed to `d3.hierarchy` directly, `support` is left
+/// out of the nodes without one
+pub(crate) fn to_json(tree: &BinaryT) -> String {
+    let root = tree
+        .root_index
+        .expect("root node is none, which means the clustering is not done");
+    let mut out = String::new();
+    write_node(tree, root, &mut out);
+    out
+}
+
+/// Generating `{nodes, edges}` lists, the id of a node is it's index
+/// in the arena and each edge goes from the parent to the child
+pub(crate) fn to_json_flat(tree: &BinaryT) -> String {
+    let root = tree
+        .root_index
+        .expect("root node is none, which means the clustering is not done");
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    for v in tree.pre_order_from(root) {
+        let idx = v.index;
+        nodes.push(format!(
+            "{{\"id\":{},\"name\":{},\"height\":{},\"size\":{}{}}}",
+            idx,
+            name(tree, idx),
+            tree.height(idx),
+            tree.size(idx),
+            support(tree, idx)
+        ));
+        if let (Some(p), Some(b)) = (tree.parent(idx), tree.branch_length(idx)) {
+            edges.push(format!(
+                "{{\"source\":{},\"target\":{},\"length\":{}}}",
+                p, idx, b
+            ));
+        }
+    }
+    format!(
+        "{{\"root\":{},\"nodes\":[{}],\"edges\":[{}]}}",
+        root,
+        nodes.join(","),
+        edges.join(",")
+    )
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+/// Per-leaf properties loaded from a tab separated file,
+/// the first line is the header and the first column is the leaf label
+pub(crate) struct LeafMetadata {
+    /// The names of the property columns
+    pub(crate) keys: Vec<String>,
+    values: HashMap<String, Vec<String>>,
+}
+
+impl LeafMetadata {
+    pub(crate) fn from_tsv(content: &str) -> Self {
+        let mut lines = content
+            .lines()
+            .map(|l| l.trim_end_matches('\r'))
+            .filter(|l| !l.is_empty());
+        let header = lines.next().expect("The metadata file is empty");
+        let keys = header
+            .split('\t')
+            .skip(1)
+            .map(|k| k.to_string())
+            .collect::<Vec<_>>();
+        let mut values = HashMap::new();
+        for line in lines {
+            let mut cols = line.split('\t');
+            let label = cols.next().unwrap().to_string();
+            let mut row = cols.map(|c| c.to_string()).collect::<Vec<_>>();
+            if row.len() > keys.len() {
+                panic!("Wrong format for the line of metadata: {}", line);
+            }
+            row.resize(keys.len(), String::new());
+            values.insert(label, row);
+        }
+        LeafMetadata { keys, values }
+    }
+
+    /// The non-empty properties of a leaf as `(key, value)`
+    pub(crate) fn properties(&self, label: &str) -> Vec<(&str, &str)> {
+        match self.values.get(label) {
+            None => vec![],
+            Some(row) => self
+                .keys
+                .iter()
+                .zip(row.iter())
+                .filter(|(_, v)| !v.is_empty())
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect(),
+        }
+    }
+
+    /// The value of one property of a leaf
+    pub(crate) fn get(&self, label: &str, key: &str) -> Option<&str> {
+        let pos = self.keys.iter().position(|k| k == key)?;
+        self.values
+            .get(label)
+            .map(|row| row[pos].as_str())
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Renaming the leafs found in `mapping`
+    pub(crate) fn relabel(&mut self, mapping: &HashMap<&str, &str>) {
+        self.values = self
+            .values
+            .drain()
+            .map(|(label, row)| match mapping.get(label.as_str()) {
+                Some(new) => (new.to_string(), row),
+                None => (label, row),
+            })
+            .collect();
+    }
+}
@@ -0,0 +1,107 @@
+use super::Xml::{escape, to_ncname, XmlOptions};
+use crate::BinaryTree::BinaryTree::BinaryT;
+
+/// A `LiteralMeta` element
+fn literal_meta(indent: &str, property: &str, datatype: &str, content: &str) -> String {
+    format!(
+        "{}<meta xsi:type=\"nex:LiteralMeta\" property=\"{}\" datatype=\"xsd:{}\" content=\"{}\"/>\n",
+        indent,
+        property,
+        datatype,
+        escape(content)
+    )
+}
+
+/// Generating a NeXML document with an `otus` block for the leafs
+/// and a `FloatTree` whose node ids are the indexes in the arena
+pub(crate) fn to_nexml(tree: &BinaryT, options: &XmlOptions) -> String {
+    let root = tree
+        .root_index
+        .expect("root node is none, which means the clustering is not done");
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<nex:nexml version=\"0.9\" xmlns=\"http://www.nexml.org/2009\" xmlns:nex=\"http://www.nexml.org/2009\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xmlns:xsd=\"http://www.w3.org/2001/XMLSchema#\" xmlns:upgma=\"urn:upgma:\" xmlns:meta=\"urn:upgma:meta:\">\n");
+
+    // Pre-order so that a parent always comes before it's children
+    let nodes = tree
+        .pre_order_from(root)
+        .map(|v| v.index)
+        .collect::<Vec<_>>();
+
+    out.push_str("  <otus id=\"otus1\">\n");
+    for idx in nodes.iter() {
+        if let Some(l) = tree.label(*idx) {
+            let properties = options
+                .metadata
+                .map(|m| m.properties(l))
+                .unwrap_or_default();
+            if properties.is_empty() {
+                out.push_str(&format!(
+                    "    <otu id=\"otu{}\" label=\"{}\"/>\n",
+                    idx,
+                    escape(l)
+                ));
+            } else {
+                out.push_str(&format!(
+                    "    <otu id=\"otu{}\" label=\"{}\">\n",
+                    idx,
+                    escape(l)
+                ));
+                for (k, v) in properties {
+                    let property = format!("meta:{}", to_ncname(k));
+                    out.push_str(&literal_meta("      ", &property, "string", v));
+                }
+                out.push_str("    </otu>\n");
+            }
+        }
+    }
+    out.push_str("  </otus>\n");
+
+    out.push_str("  <trees id=\"trees1\" otus=\"otus1\">\n");
+    out.push_str("    <tree id=\"tree1\" xsi:type=\"nex:FloatTree\">\n");
+    for idx in nodes.iter() {
+        let mut attrs = format!("id=\"n{}\"", idx);
+        if let Some(l) = tree.label(*idx) {
+            attrs.push_str(&format!(" label=\"{}\" otu=\"otu{}\"", escape(l), idx));
+        }
+        if *idx == root {
+            attrs.push_str(" root=\"true\"");
+        }
+        let mut metas = String::new();
+        if options.heights {
+            let height = tree.height(*idx).to_string();
+            let size = tree.size(*idx).to_string();
+            metas.push_str(&literal_meta("        ", "upgma:height", "double", &height));
+            metas.push_str(&literal_meta("        ", "upgma:size", "integer", &size));
+        }
+        if let Some(s) = tree.support(*idx) {
+            let support = s.to_string();
+            metas.push_str(&literal_meta(
+                "        ",
+                "upgma:support",
+                "double",
+                &support,
+            ));
+        }
+        if metas.is_empty() {
+            out.push_str(&format!("      <node {}/>\n", attrs));
+        } else {
+            out.push_str(&format!("      <node {}>\n", attrs));
+            out.push_str(&metas);
+            out.push_str("      </node>\n");
+        }
+    }
+    for idx in nodes.iter() {
+        if let Some(p) = tree.parent(*idx) {
+            let length = match (options.branch_lengths, tree.branch_length(*idx)) {
+                (true, Some(b)) => format!(" length=\"{}\"", b),
+                (_, _) => "".to_string(),
+            };
+            out.push_str(&format!(
+                "      <edge id=\"e{}\" source=\"n{}\" target=\"n{}\"{}/>\n",
+                idx, p, idx, length
+            ));
+        }
+    }
+    out.push_str("    </tree>\n  </trees>\n</nex:nexml>\n");
+    out
+}
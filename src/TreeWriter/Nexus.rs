@@ -0,0 +1,93 @@
+use crate::BinaryTree::BinaryTree::{BinaryT, NodeIndex};
+use std::collections::HashMap;
+
+/// Options of the Nexus writer
+pub(crate) struct NexusOptions {
+    /// Writing `:length` after each node
+    pub(crate) branch_lengths: bool,
+    /// Writing `[&height=..,size=..]` comments after each node
+    pub(crate) annotations: bool,
+}
+
+/// Quoting a label if it's not a plain Nexus token,
+/// a single quote inside the label is written twice
+pub(crate) fn quote_label(label: &str) -> String {
+    let plain = !label.is_empty()
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if plain {
+        label.to_string()
+    } else {
+        format!("'{}'", label.replace('\'', "''"))
+    }
+}
+
+/// Writing the sub-tree under `index` in Newick format,
+/// leafs are written as their token in the translate table
+fn write_node(
+    tree: &BinaryT,
+    index: NodeIndex,
+    translate: &HashMap<&str, usize>,
+    options: &NexusOptions,
+    out: &mut String,
+) {
+    match tree.label(index) {
+        Some(l) => out.push_str(&translate[l].to_string()),
+        None => {
+            out.push('(');
+            for (i, c) in tree.children(index).into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_node(tree, c, translate, options, out);
+            }
+            out.push(')');
+            if let Some(s) = tree.support(index) {
+                out.push_str(&s.to_string());
+            }
+        }
+    }
+    if options.annotations {
+        out.push_str(&format!(
+            "[&height={},size={}]",
+            tree.height(index),
+            tree.size(index)
+        ));
+    }
+    if options.branch_lengths {
+        if let Some(b) = tree.branch_length(index) {
+            out.push_str(&format!(":{}", b));
+        }
+    }
+}
+
+/// Generating a Nexus file with a TAXA block and a TREES block,
+/// the taxa are numbered from 1 in the leaf order of the tree
+pub(crate) fn to_nexus(tree: &BinaryT, options: &NexusOptions) -> String {
+    let root = tree
+        .root_index
+        .expect("root node is none, which means the clustering is not done");
+    let labels = tree.leaf_labels();
+    let translate = labels
+        .iter()
+        .enumerate()
+        .map(|(i, l)| (*l, i + 1))
+        .collect::<HashMap<_, _>>();
+
+    let mut out = String::from("#NEXUS\n\nBEGIN TAXA;\n");
+    out.push_str(&format!("\tDIMENSIONS NTAX={};\n", labels.len()));
+    out.push_str("\tTAXLABELS\n");
+    for l in labels.iter() {
+        out.push_str(&format!("\t\t{}\n", quote_label(l)));
+    }
+    out.push_str("\t;\nEND;\n\nBEGIN TREES;\n\tTRANSLATE\n");
+    for (i, l) in labels.iter().enumerate() {
+        let sep = if i + 1 == labels.len() { "" } else { "," };
+        out.push_str(&format!("\t\t{} {}{}\n", i + 1, quote_label(l), sep));
+    }
+    out.push_str("\t;\n\tTREE tree1 = [&R] ");
+    write_node(tree, root, &translate, options, &mut out);
+    out.push_str(";\nEND;\n");
+    out
+}
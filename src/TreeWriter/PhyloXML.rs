@@ -0,0 +1,71 @@
+use super::Xml::{escape, to_ncname, XmlOptions};
+use crate::BinaryTree::BinaryTree::{BinaryT, NodeIndex};
+
+/// Writing a `clade` element and all the clades under it
+fn write_clade(
+    tree: &BinaryT,
+    index: NodeIndex,
+    options: &XmlOptions,
+    depth: usize,
+    out: &mut String,
+) {
+    let indent = "  ".repeat(depth);
+    out.push_str(&format!("{}<clade>\n", indent));
+    let label = tree.label(index);
+    if let Some(l) = label {
+        out.push_str(&format!("{}  <name>{}</name>\n", indent, escape(l)));
+    }
+    if options.branch_lengths {
+        if let Some(b) = tree.branch_length(index) {
+            out.push_str(&format!(
+                "{}  <branch_length>{}</branch_length>\n",
+                indent, b
+            ));
+        }
+    }
+    if let Some(s) = tree.support(index) {
+        out.push_str(&format!(
+            "{}  <confidence type=\"{}\">{}</confidence>\n",
+            indent, options.support_type, s
+        ));
+    }
+    if options.heights {
+        out.push_str(&format!(
+            "{}  <property ref=\"upgma:height\" datatype=\"xsd:double\" applies_to=\"clade\">{}</property>\n",
+            indent,
+            tree.height(index)
+        ));
+        out.push_str(&format!(
+            "{}  <property ref=\"upgma:size\" datatype=\"xsd:integer\" applies_to=\"clade\">{}</property>\n",
+            indent,
+            tree.size(index)
+        ));
+    }
+    if let (Some(l), Some(m)) = (label, options.metadata) {
+        for (k, v) in m.properties(l) {
+            out.push_str(&format!(
+                "{}  <property ref=\"meta:{}\" datatype=\"xsd:string\" applies_to=\"clade\">{}</property>\n",
+                indent,
+                to_ncname(k),
+                escape(v)
+            ));
+        }
+    }
+    for c in tree.children(index) {
+        write_clade(tree, c, options, depth + 1, out);
+    }
+    out.push_str(&format!("{}</clade>\n", indent));
+}
+
+/// Generating a PhyloXML document with one rooted phylogeny
+pub(crate) fn to_phyloxml(tree: &BinaryT, options: &XmlOptions) -> String {
+    let root = tree
+        .root_index
+        .expect("root node is none, which means the clustering is not done");
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<phyloxml xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xsi:schemaLocation=\"http://www.phyloxml.org http://www.phyloxml.org/1.10/phyloxml.xsd\" xmlns=\"http://www.phyloxml.org\">\n");
+    out.push_str("  <phylogeny rooted=\"true\">\n");
+    write_clade(tree, root, options, 2, &mut out);
+    out.push_str("  </phylogeny>\n</phyloxml>\n");
+    out
+}
@@ -0,0 +1,47 @@
+use super::Metadata::LeafMetadata;
+
+/// Options shared by the XML based writers
+pub(crate) struct XmlOptions<'m> {
+    /// Writing the length of the branch above each node
+    pub(crate) branch_lengths: bool,
+    /// Writing the height and size of each clade
+    pub(crate) heights: bool,
+    /// Per-leaf properties
+    pub(crate) metadata: Option<&'m LeafMetadata>,
+    /// The kind of the clade supports, the `type` of a PhyloXML confidence
+    pub(crate) support_type: &'static str,
+}
+
+/// Escaping the special characters of XML text and attributes
+pub(crate) fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Making a metadata key usable as the local part of a CURIE
+pub(crate) fn to_ncname(key: &str) -> String {
+    let mut out = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if !out.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        out.insert(0, '_');
+    }
+    out
+}
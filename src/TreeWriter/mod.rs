@@ -0,0 +1,7 @@
+pub(crate) mod Dot;
+pub(crate) mod Json;
+pub(crate) mod Metadata;
+pub(crate) mod NeXML;
+pub(crate) mod Nexus;
+pub(crate) mod PhyloXML;
+pub(crate) mod Xml;
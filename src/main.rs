@@ -1,17 +1,47 @@
-use std::env;
-use std::fs::{File, OpenOptions};
-use std::io::{self, BufReader, Lines};
+use std::fs::File;
+use std::io::{self, BufReader};
 use std::io::{prelude::*, BufWriter};
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
+// The modules are named after the files, which are in CamelCase
+#[allow(non_snake_case)]
 mod BinaryTree;
+#[allow(non_snake_case)]
+mod Diagnostics;
+#[allow(non_snake_case)]
 mod MaxHeap;
+#[allow(non_snake_case)]
+mod Render;
+#[allow(non_snake_case)]
+mod Resample;
+#[allow(non_snake_case)]
+mod TreeWriter;
 
+use clap::{ArgEnum, Parser};
 use std::collections::{HashMap, HashSet};
 use ABtree::BTree;
 
-use BinaryTree::BinaryTree::{ArcStr, BinaryT, HeapPair, Node, NodeIndex};
+use BinaryTree::BinaryTree::{
+    dense_matrix, ArcStr, BinaryT, HeapKey, HeapPair, Node, NodeIndex, TraceFormat,
+};
+use BinaryTree::Consensus::Consensus;
+use BinaryTree::LeafOrder::Ladderize;
+use BinaryTree::Support::SplitCounter;
+use Diagnostics::Metric::{check_metric, MetricOptions};
+use Diagnostics::Quality::{assign_clusters, cluster_quality, sweep_k};
 use MaxHeap::MaxHeap::MaxHeap as Maxheap;
+use Render::Ascii::{to_ascii, AsciiOptions};
+use Render::Heatmap::{to_heatmap_pgm, to_heatmap_svg, HeatmapOptions};
+use Render::Svg::{to_svg, Layout, SvgOptions};
+use Resample::Columns::Columns;
+use Resample::Random::{bootstrap_weights, jackknife_weights, SplitMix64};
+use TreeWriter::Dot::to_dot;
+use TreeWriter::Json::{to_json, to_json_flat};
+use TreeWriter::Metadata::LeafMetadata;
+use TreeWriter::NeXML::to_nexml;
+use TreeWriter::Nexus::{to_nexus, NexusOptions};
+use TreeWriter::PhyloXML::to_phyloxml;
+use TreeWriter::Xml::XmlOptions;
 
 /// Loading content of input file into memory
 fn read_file(path: &str) -> io::Result<String> {
@@ -32,7 +62,7 @@ fn wirte_file(path: &str, s: &str) -> io::Result<()> {
 /// Spltting the str
 /// The life time of each line should share the
 /// same lift time with the input file content
-fn strtok<'content, 'b>(s: &'b mut &'content str, delimiter: &'static str) -> &'content str {
+fn strtok<'content>(s: &mut &'content str, delimiter: &'static str) -> &'content str {
     if let Some(i) = s.find(delimiter) {
         let prefix = &s[..i];
         let suffix = &s[(i + delimiter.len())..];
@@ -45,31 +75,632 @@ fn strtok<'content, 'b>(s: &'b mut &'content str, delimiter: &'static str) -> &'
     }
 }
 
-fn clustering(tsv_file: &String) -> io::Result<()> {
-    //let mut content = "s1 s2 -2\ns1 s3 -5\ns1 s4 -7\ns1 s5 -9\ns2 s3 -4\ns2 s4 -6\ns2 s5 -7\ns3 s4 -4\ns3 s5 -6\ns4 s5 -3\n".to_string();
-    let content = read_file(&tsv_file)?;
+/// How NaN and ±inf values in the input are handled
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum NonFinitePolicy {
+    /// Stop with an error on the first non-finite value
+    Reject,
+    /// Drop the pair as if it was never given
+    Missing,
+    /// Replace the value with the least similar finite value of the input,
+    /// so the pair is merged as late as possible
+    MaxDistance,
+}
+
+/// How a pair given in both directions with different values is handled
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum AsymmetricPolicy {
+    /// Stop with an error on the first asymmetric pair
+    Error,
+    /// Take the smaller value
+    Min,
+    /// Take the larger value
+    Max,
+    /// Take the mean of the two values
+    Mean,
+    /// Take the value whose first sample appears first in the input
+    Upper,
+    /// Take the value whose first sample appears later in the input
+    Lower,
+}
+
+/// How a line pairing a sample with itself is handled
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SelfPairPolicy {
+    /// Drop the line, the diagonal carries no information
+    Ignore,
+    /// Stop with an error
+    Error,
+}
+
+/// How a pair given more than once in the same direction is handled
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum DuplicatePolicy {
+    /// Stop with an error if the values of the lines differ
+    Error,
+    /// Keep the first line of the pair
+    KeepFirst,
+    /// Keep the last line of the pair
+    KeepLast,
+}
+
+/// The format of the output tree
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    Newick,
+    Nexus,
+    Phyloxml,
+    Nexml,
+    /// Nested objects for `d3.hierarchy`
+    Json,
+    /// Lists of nodes and edges
+    JsonFlat,
+    /// A dendrogram drawn with box-drawing characters
+    Ascii,
+    /// A dendrogram picture
+    Svg,
+    /// A Graphviz digraph
+    Dot,
+}
+
+/// A parsed line of `(from, to, value)`, the value is `None`
+/// if the pair is treated as missing
+type Row<'content> = (&'content str, &'content str, Option<f64>);
+
+/// The value of each pair, keyed by the sample appearing first
+type PairValues<'content> = BTree<ArcStr<'content>, BTree<ArcStr<'content>, f64>>;
+
+/// Parsing a fraction between 0 and 1 inclusive
+fn parse_fraction(s: &str) -> Result<f64, String> {
+    let v = s.parse::<f64>().map_err(|e| e.to_string())?;
+    if (0.0..=1.0).contains(&v) {
+        Ok(v)
+    } else {
+        Err(format!("{} is not a fraction between 0 and 1", v))
+    }
+}
+
+/// Parsing a fraction strictly between 0 and 1
+fn parse_open_fraction(s: &str) -> Result<f64, String> {
+    let v = parse_fraction(s)?;
+    if v > 0.0 && v < 1.0 {
+        Ok(v)
+    } else {
+        Err(format!("{} is not a fraction strictly between 0 and 1", v))
+    }
+}
+
+#[derive(Parser)]
+#[clap(version, about = "UPGMA clustering of tab separated paired values")]
+struct Args {
+    /// Input file, each line is `sample\tsample\tvalue`
+    #[clap(required_unless_present_any = &["features", "alignment"])]
+    tsv: Option<String>,
+
+    /// Tab separated features with a header line instead of the input file,
+    /// the first column is the sample label and the value of a pair is the
+    /// negative Euclidean distance
+    #[clap(long, group = "columns", conflicts_with = "tsv")]
+    features: Option<String>,
+
+    /// Aligned sequences in FASTA format instead of the input file, the value
+    /// of a pair is the negative proportion of differing sites without gaps
+    #[clap(long, group = "columns", conflicts_with = "tsv")]
+    alignment: Option<String>,
+
+    /// Policy for NaN and ±inf values
+    #[clap(long, arg_enum, default_value = "reject")]
+    non_finite: NonFinitePolicy,
+
+    /// Maximum fraction of the n(n-1)/2 pairs that may be missing,
+    /// a cluster value is then averaged over the present pairs only
+    #[clap(long, default_value = "0.0", parse(try_from_str = parse_fraction))]
+    max_missing: f64,
+
+    /// Policy for a line like `s1\ts1\t0`
+    #[clap(long, arg_enum, default_value = "ignore")]
+    self_pair: SelfPairPolicy,
+
+    /// Policy for a pair given more than once in the same direction
+    #[clap(long, arg_enum, default_value = "keep-last")]
+    duplicate: DuplicatePolicy,
+
+    /// Policy for a pair given as both `a\tb` and `b\ta` with different values
+    #[clap(long, arg_enum, default_value = "error")]
+    asymmetric: AsymmetricPolicy,
+
+    /// Number of the largest asymmetries to report
+    #[clap(long, default_value = "5")]
+    report_asymmetric: usize,
+
+    /// The value of a sample paired with itself, the height of a
+    /// merge is half of the difference between this and the merge value
+    #[clap(long, default_value = "0.0", allow_hyphen_values = true)]
+    self_value: f64,
+
+    /// Format of the output tree, only the `neato` layout draws the `dot`
+    /// edges to the branch lengths, the others show them as edge labels
+    #[clap(long, arg_enum, default_value = "newick")]
+    format: OutputFormat,
+
+    /// Writing the tree into this file instead of stdout
+    #[clap(long, short)]
+    output: Option<String>,
+
+    /// Writing branch lengths into the tree, as `:length` for Newick and Nexus
+    #[clap(long)]
+    branch_lengths: bool,
+
+    /// Writing node heights and sizes, as `[&key=value]` comments for Nexus
+    #[clap(long)]
+    annotate: bool,
+
+    /// Tab separated per-leaf properties with a header line,
+    /// the first column is the sample label
+    #[clap(long)]
+    leaf_metadata: Option<String>,
+
+    /// Number of columns of the text dendrogram,
+    /// the default is `$COLUMNS` or 80
+    #[clap(long)]
+    width: Option<usize>,
+
+    /// Drawing a height axis under the text dendrogram
+    #[clap(long)]
+    axis: bool,
+
+    /// Layout of the SVG dendrogram
+    #[clap(long, arg_enum, default_value = "horizontal")]
+    layout: Layout,
+
+    /// Font size of the SVG labels in pixels
+    #[clap(long, default_value = "12")]
+    font_size: f64,
+
+    /// Length from the root to the leafs of the SVG dendrogram in pixels
+    #[clap(long, default_value = "600")]
+    svg_size: f64,
+
+    /// Colouring the SVG leafs by this column of `--leaf-metadata`
+    #[clap(long, requires = "leaf-metadata")]
+    colour_by: Option<String>,
+
+    /// Highlighting the flat clusters from cutting the tree at this height
+    #[clap(long, conflicts_with = "cut-k", group = "cut")]
+    cut_height: Option<f64>,
+
+    /// Highlighting the flat clusters from cutting the tree into k clusters
+    #[clap(long, group = "cut")]
+    cut_k: Option<usize>,
+
+    /// Writing a heatmap of the values reordered by the leaf order into
+    /// this file, it's a PGM picture if the name ends with `.pgm` else SVG
+    #[clap(long)]
+    heatmap: Option<String>,
+
+    /// Side of a heatmap cell in pixels
+    #[clap(long, default_value = "12")]
+    cell_size: usize,
+
+    /// Writing each merge step into this file
+    #[clap(long)]
+    trace: Option<String>,
+
+    /// Format of the merge trace
+    #[clap(long, arg_enum, default_value = "tsv")]
+    trace_format: TraceFormat,
+
+    /// Moving the root onto this leaf
+    #[clap(long, group = "rooting")]
+    root_leaf: Option<String>,
+
+    /// Moving the root to the middle of the longest path between two leafs
+    #[clap(long, group = "rooting")]
+    midpoint_root: bool,
+
+    /// Moving the root above the clade of these comma separated leafs
+    #[clap(long, group = "rooting", use_value_delimiter = true)]
+    outgroup: Vec<String>,
+
+    /// Flipping the sub-trees to minimise the distances of adjacent leafs
+    /// before any output
+    #[clap(long)]
+    optimal_leaf_order: bool,
+
+    /// Ordering the children of each node by their sizes before any output
+    #[clap(long, arg_enum, conflicts_with = "optimal-leaf-order")]
+    ladderize: Option<Ladderize>,
+
+    /// Ordering the children of each node by their smallest leaf label
+    /// before any output, ties of `--ladderize` are broken the same way
+    #[clap(long, conflicts_with = "optimal-leaf-order")]
+    sort_labels: bool,
+
+    /// Writing the leaf labels in the order of the tree into this file,
+    /// one label per line
+    #[clap(long)]
+    leaf_order: Option<String>,
+
+    /// Writing only the clade of the lowest common ancestor of these comma
+    /// separated leafs, it's height and size are printed to stderr
+    #[clap(long, use_value_delimiter = true)]
+    clade: Vec<String>,
+
+    /// Printing the height, size and leafs of each clade containing this
+    /// leaf to stderr, from the leaf itself up to the root
+    #[clap(long)]
+    clades_of: Option<String>,
+
+    /// Keeping only the leafs listed in this file, one label per line,
+    /// an inner node left with one child is replaced by the child
+    #[clap(long)]
+    prune_to: Option<String>,
+
+    /// Renaming the leafs of the outputs by this file of `old\tnew` lines,
+    /// the other options take the old labels
+    #[clap(long)]
+    relabel: Option<String>,
+
+    /// Collapsing the inner branches shorter than this into
+    /// multifurcating nodes after any reordering
+    #[clap(long)]
+    collapse_length: Option<f64>,
+
+    /// Clustering another input file of the same kind as the input, which
+    /// is pairs, `--features` or `--alignment`, with the same policies and
+    /// printing the distances between the two trees over their shared leafs
+    #[clap(long)]
+    compare: Option<String>,
+
+    /// Writing the fraction of this many bootstrap replicates of the
+    /// columns having the bipartition of the leafs made by each inner
+    /// branch as the label of it's node, so a rerooted tree keeps them
+    #[clap(long, requires = "columns", group = "replicates")]
+    bootstrap: Option<usize>,
+
+    /// Writing the fraction of this many jackknife replicates of the
+    /// columns having the bipartition of the leafs made by each inner
+    /// branch as the label of it's node, so a rerooted tree keeps them
+    #[clap(long, requires = "columns", group = "replicates")]
+    jackknife: Option<usize>,
+
+    /// Fraction of the columns deleted by each jackknife replicate
+    #[clap(long, default_value = "0.5", parse(try_from_str = parse_open_fraction))]
+    jackknife_fraction: f64,
+
+    /// Writing the consensus of the bipartitions of the replicate trees
+    /// instead of the tree of all the columns, rooted like that tree if
+    /// it's root fits, only the replicates of `--bootstrap` or
+    /// `--jackknife` can be combined
+    #[clap(long, arg_enum, requires = "replicates")]
+    consensus: Option<Consensus>,
+
+    /// Seed for drawing the columns of the replicates
+    #[clap(long, default_value = "1")]
+    seed: u64,
+
+    /// Collapsing the inner branches whose support is below this
+    /// into multifurcating nodes after any reordering
+    #[clap(long)]
+    collapse_support: Option<f64>,
+
+    /// Checking the triangle inequality, the ultrametric inequality and
+    /// the four-point condition on the distances of the input, which are
+    /// the self value minus the values, and reporting the violations
+    #[clap(long)]
+    check_metric: bool,
+
+    /// Number of the worst violations to report for each property
+    #[clap(long, default_value = "5")]
+    report_violations: usize,
+
+    /// Ignoring the violations not larger than this
+    #[clap(long, default_value = "1e-9")]
+    metric_tolerance: f64,
+
+    /// Checking a random sample of this many triplets or quartets
+    /// if there are more of them
+    #[clap(long, default_value = "1000000")]
+    max_tuples: usize,
+
+    /// Printing the mean silhouette, the Dunn index and the Davies-Bouldin
+    /// index of the flat clusters of `--cut-height` or `--cut-k`
+    #[clap(long, requires = "cut")]
+    cluster_quality: bool,
+
+    /// Writing the silhouette of each leaf in the flat clusters of
+    /// `--cut-height` or `--cut-k` into this file
+    #[clap(long, requires = "cut")]
+    silhouettes: Option<String>,
+
+    /// Printing a table of the cluster scores of cutting the tree
+    /// into each number of clusters from 2 to this
+    #[clap(long)]
+    sweep_k: Option<usize>,
+}
+
+/// Parsing each line of the content into `(from, to, value)`
+/// and applying the non-finite value policy
+fn parse_rows(content: &str, non_finite: NonFinitePolicy) -> Vec<Row<'_>> {
     let row_sep = "\n";
     let data_sep = "\t";
-    let mut lines = &content[..]; // life time is 'content
-    let mut paired_values_dict: BTree<ArcStr<'_>, BTree<ArcStr<'_>, f64>> = BTree::new(4);
-    let mut paired_values_heap: Maxheap<f64, HeapPair> = Maxheap::new();
-    let mut all_samples: HashSet<ArcStr<'_>> = HashSet::new();
-    let mut index_node_dict: HashMap<NodeIndex, Node<'_>> = HashMap::new();
-    let mut node_index_dict: HashMap<Node<'_>, NodeIndex> = HashMap::new();
-    let mut node_index: NodeIndex = 0;
-    while lines.len() > 0 {
+    let mut lines = content; // life time is 'content
+    let mut rows = Vec::new();
+    while !lines.is_empty() {
         // So the life time of each row is 'content
-        let mut row = strtok(&mut lines, row_sep);
+        let line = strtok(&mut lines, row_sep);
+        let mut row = line;
         let mut row_vec = Vec::new();
-        while row.len() > 0 {
+        while !row.is_empty() {
             row_vec.push(strtok(&mut row, data_sep))
         }
         if row_vec.len() != 3 {
-            panic!("Wrong format for the line of content: {}", row);
+            panic!("Wrong format for the line of content: {}", line);
+        }
+        let value = row_vec.pop().unwrap().trim();
+        let value = value
+            .parse::<f64>()
+            .unwrap_or_else(|_| panic!("Can not parse the value of line: {}", line));
+        let to_str = row_vec.pop().unwrap();
+        let from_str = row_vec.pop().unwrap();
+        if value.is_finite() {
+            rows.push((from_str, to_str, Some(value)));
+            continue;
+        }
+        match non_finite {
+            NonFinitePolicy::Reject => {
+                panic!("Non-finite value found in the line of content: {}", line)
+            }
+            NonFinitePolicy::Missing => rows.push((from_str, to_str, None)),
+            NonFinitePolicy::MaxDistance => rows.push((from_str, to_str, Some(f64::NEG_INFINITY))),
+        }
+    }
+    if non_finite == NonFinitePolicy::MaxDistance {
+        // The heap pops the largest value first, so the
+        // least similar value is the minimum finite one
+        let least = rows
+            .iter()
+            .filter_map(|r| r.2)
+            .filter(|v| v.is_finite())
+            .reduce(f64::min)
+            .unwrap_or(0.0);
+        rows.iter_mut()
+            .filter(|r| r.2 == Some(f64::NEG_INFINITY))
+            .for_each(|r| r.2 = Some(least));
+    }
+    rows
+}
+
+/// Dropping self pairs and duplicated lines according to the policies,
+/// a summary of the discarded lines is printed to stderr
+fn discard_self_and_duplicates<'content>(
+    rows: Vec<Row<'content>>,
+    self_pair: SelfPairPolicy,
+    duplicate: DuplicatePolicy,
+) -> Vec<Row<'content>> {
+    let mut self_nums: usize = 0;
+    let mut duplicate_nums: usize = 0;
+    let mut conflict_nums: usize = 0;
+    // The index in `kept` of each directed pair
+    let mut seen: HashMap<(&str, &str), usize> = HashMap::new();
+    let mut kept: Vec<Option<Row<'content>>> = Vec::new();
+    for r in rows {
+        if r.0 == r.1 {
+            if self_pair == SelfPairPolicy::Error {
+                panic!("Self pair found for the sample: {}", r.0);
+            }
+            self_nums += 1;
+            continue;
+        }
+        let pos = match seen.get(&(r.0, r.1)) {
+            None => {
+                seen.insert((r.0, r.1), kept.len());
+                kept.push(Some(r));
+                continue;
+            }
+            Some(pos) => *pos,
+        };
+        duplicate_nums += 1;
+        let old = kept[pos].unwrap();
+        let conflicting = old.2.map(|v| v.to_bits()) != r.2.map(|v| v.to_bits());
+        if conflicting {
+            conflict_nums += 1;
+        }
+        match duplicate {
+            DuplicatePolicy::Error if conflicting => {
+                let show = |v: Option<f64>| v.map_or("missing".to_string(), |v| v.to_string());
+                panic!(
+                    "Conflicting values {} and {} found for the pair of {} and {}",
+                    show(old.2),
+                    show(r.2),
+                    r.0,
+                    r.1
+                )
+            }
+            DuplicatePolicy::KeepLast => {
+                // Moving the pair to the place of the last line
+                kept[pos] = None;
+                seen.insert((r.0, r.1), kept.len());
+                kept.push(Some(r));
+            }
+            _ => {}
+        }
+    }
+    if self_nums + duplicate_nums > 0 {
+        eprintln!(
+            "Discarded {} self pairs and {} duplicated lines ({} with conflicting values)",
+            self_nums, duplicate_nums, conflict_nums
+        );
+    }
+    kept.into_iter().flatten().collect()
+}
+
+/// Merging the pairs given in both directions into one row
+/// according to the asymmetric policy, the merged row takes the
+/// place of the first appearance of the pair.
+/// The largest `report` differences are printed to stderr
+fn resolve_asymmetric<'content>(
+    mut rows: Vec<Row<'content>>,
+    policy: AsymmetricPolicy,
+    report: usize,
+) -> Vec<Row<'content>> {
+    // The order of first appearance decides the triangle of a pair
+    let mut order: HashMap<&str, usize> = HashMap::new();
+    for r in rows.iter() {
+        for s in [r.0, r.1] {
+            let n = order.len();
+            order.entry(s).or_insert(n);
+        }
+    }
+    let mut groups: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+    for (i, r) in rows.iter().enumerate() {
+        let key = if order[r.0] <= order[r.1] {
+            (r.0, r.1)
+        } else {
+            (r.1, r.0)
+        };
+        groups.entry(key).or_default().push(i);
+    }
+
+    let mut asymmetries: Vec<(&str, &str, f64, f64)> = Vec::new();
+    let mut dropped: HashSet<usize> = HashSet::new();
+    for (key, indexes) in groups.iter() {
+        let upper = indexes.iter().rev().find(|i| rows[**i].0 == key.0);
+        let lower = indexes.iter().rev().find(|i| rows[**i].0 == key.1);
+        let (upper, lower) = match (upper, lower) {
+            (Some(u), Some(l)) if key.0 != key.1 => (rows[*u].2, rows[*l].2),
+            (_, _) => continue,
+        };
+        let value = match (upper, lower) {
+            (Some(u), Some(l)) => {
+                if u != l {
+                    asymmetries.push((key.0, key.1, u, l));
+                }
+                match policy {
+                    AsymmetricPolicy::Error => Some(u),
+                    AsymmetricPolicy::Min => Some(u.min(l)),
+                    AsymmetricPolicy::Max => Some(u.max(l)),
+                    AsymmetricPolicy::Mean => Some((u + l) / 2.0),
+                    AsymmetricPolicy::Upper => Some(u),
+                    AsymmetricPolicy::Lower => Some(l),
+                }
+            }
+            (u, l) => u.or(l),
+        };
+        let first = indexes[0];
+        rows[first] = (key.0, key.1, value);
+        dropped.extend(indexes[1..].iter());
+    }
+
+    if !asymmetries.is_empty() {
+        asymmetries.sort_by(|a, b| {
+            let da = (a.2 - a.3).abs();
+            let db = (b.2 - b.3).abs();
+            db.total_cmp(&da).then_with(|| (a.0, a.1).cmp(&(b.0, b.1)))
+        });
+        eprintln!("{} pairs have asymmetric values", asymmetries.len());
+        for a in asymmetries.iter().take(report) {
+            eprintln!("{}\t{}\t{}\t{}", a.0, a.1, a.2, a.3);
         }
-        let value = row_vec.pop().unwrap().trim().parse::<f64>().unwrap();
-        let to_str = Arc::new(row_vec.pop().unwrap());
-        let from_str = Arc::new(row_vec.pop().unwrap());
+        if policy == AsymmetricPolicy::Error {
+            let a = asymmetries[0];
+            panic!(
+                "Asymmetric values {} and {} found for the pair of {} and {}",
+                a.2, a.3, a.0, a.1
+            );
+        }
+    }
+
+    rows.into_iter()
+        .enumerate()
+        .filter(|(i, _)| !dropped.contains(i))
+        .map(|(_, r)| r)
+        .collect()
+}
+
+/// Parsing the lines of `old\tnew` into a map of the old label to the new
+fn parse_mapping(content: &str) -> HashMap<&str, &str> {
+    let mut mapping = HashMap::new();
+    for line in content
+        .lines()
+        .map(|l| l.trim_end_matches('\r'))
+        .filter(|l| !l.is_empty())
+    {
+        let mut cols = line.split('\t');
+        let (old, new) = match (cols.next(), cols.next(), cols.next()) {
+            (Some(old), Some(new), None) => (old, new),
+            _ => panic!("Wrong format for the line of mapping: {}", line),
+        };
+        if mapping.insert(old, new).is_some() {
+            panic!("The label {} is mapped more than once", old);
+        }
+    }
+    mapping
+}
+
+/// The paired values with the samples renamed by the mapping
+fn relabel_pairs<'content>(
+    dict: &PairValues<'content>,
+    mapping: &HashMap<&str, &'content str>,
+) -> PairValues<'content> {
+    let rename = |s: &ArcStr<'content>| Arc::new(mapping.get(**s).copied().unwrap_or(**s));
+    let mut out: PairValues<'content> = BTree::new(4);
+    for (from, inner) in dict.iter() {
+        let mut renamed = BTree::new(4);
+        for (to, value) in inner.iter() {
+            renamed.insert(rename(to), *value);
+        }
+        out.insert(rename(from), renamed);
+    }
+    out
+}
+
+/// Reading the rows of an input file after applying all the input policies
+fn read_rows<'content>(content: &'content str, args: &Args) -> Vec<Row<'content>> {
+    let rows = parse_rows(content, args.non_finite);
+    let rows = discard_self_and_duplicates(rows, args.self_pair, args.duplicate);
+    resolve_asymmetric(rows, args.asymmetric, args.report_asymmetric)
+}
+
+/// The columns of the content if the input is `--features` or `--alignment`
+fn read_columns<'content>(content: &'content str, args: &Args) -> Option<Columns<'content>> {
+    if args.features.is_some() {
+        Some(Columns::from_tsv(content))
+    } else if args.alignment.is_some() {
+        Some(Columns::from_fasta(content))
+    } else {
+        None
+    }
+}
+
+/// The rows of all the columns if there are, else of the paired values
+fn input_rows<'content>(
+    content: &'content str,
+    columns: &Option<Columns<'content>>,
+    args: &Args,
+) -> Vec<Row<'content>> {
+    match columns {
+        Some(c) => c.rows(&vec![1; c.width()]),
+        None => read_rows(content, args),
+    }
+}
+
+/// Clustering the rows into a tree, the merge trace is written and the
+/// missing pairs and sparse merges are reported only if `main` is set
+fn build_tree<'content>(
+    rows: Vec<Row<'content>>,
+    args: &Args,
+    main: bool,
+) -> io::Result<(BinaryT<'content>, PairValues<'content>)> {
+    let mut paired_values_dict: PairValues<'_> = BTree::new(4);
+    let mut paired_values_heap: Maxheap<HeapKey, HeapPair> = Maxheap::new();
+    let mut all_samples: HashSet<ArcStr<'_>> = HashSet::new();
+    let mut index_node_dict: HashMap<NodeIndex, Node<'_>> = HashMap::new();
+    let mut node_index_dict: HashMap<Node<'_>, NodeIndex> = HashMap::new();
+    let mut node_index: NodeIndex = 0;
+    for (from_str, to_str, value) in rows {
+        let to_str = Arc::new(to_str);
+        let from_str = Arc::new(from_str);
         let from_node = Node::new_from_str(from_str.clone());
         let to_node = Node::new_from_str(to_str.clone());
 
@@ -83,6 +714,10 @@ fn clustering(tsv_file: &String) -> io::Result<()> {
             node_index_dict.insert(Node::clone(&to_node), node_index);
             node_index += 1;
         }
+        let value = match value {
+            Some(v) => v,
+            None => continue,
+        };
 
         if paired_values_dict.contains(&from_str) {
             let inner_map = paired_values_dict.get_mut(&from_str).unwrap();
@@ -95,7 +730,8 @@ fn clustering(tsv_file: &String) -> io::Result<()> {
 
         let from_index = *node_index_dict.get(&from_node).unwrap();
         let to_index = *node_index_dict.get(&to_node).unwrap();
-        paired_values_heap.insert(value, HeapPair::new(from_index, to_index));
+        let pair = HeapPair::new(from_index, to_index);
+        paired_values_heap.insert(pair.key(value), pair);
     }
 
     let n = all_samples.len();
@@ -103,34 +739,469 @@ fn clustering(tsv_file: &String) -> io::Result<()> {
     let accepted_pair_nums = paired_values_dict
         .iter()
         .map(|(_k, v)| v.len())
-        .into_iter()
-        .reduce(|x, y| x + y)
-        .unwrap_or(0);
+        .sum::<usize>();
 
-    let msg = format!("The number of input samples is {} and expected combination number is {}, but accepted combination number is {}",
-                      n, expected_combination_nums, accepted_pair_nums);
-    assert!(expected_combination_nums == accepted_pair_nums, "{}", msg);
+    let missing_nums = expected_combination_nums.saturating_sub(accepted_pair_nums);
+    let missing_fraction = if expected_combination_nums == 0 {
+        0.0
+    } else {
+        missing_nums as f64 / expected_combination_nums as f64
+    };
+    let msg = format!("The number of input samples is {} and expected combination number is {}, but accepted combination number is {} (allowed missing fraction is {})",
+                      n, expected_combination_nums, accepted_pair_nums, args.max_missing);
+    assert!(missing_fraction <= args.max_missing, "{}", msg);
+    if main && missing_nums > 0 {
+        eprintln!(
+            "{} of {} pairs ({:.2}%) are missing",
+            missing_nums,
+            expected_combination_nums,
+            missing_fraction * 100.0
+        );
+    }
+
+    let mut tree = BinaryT::new(
+        index_node_dict,
+        node_index_dict,
+        all_samples,
+        args.self_value,
+    );
 
-    let mut tree = BinaryT::new(index_node_dict, node_index_dict, all_samples);
+    if let (Some(path), true) = (&args.trace, main) {
+        let writer = BufWriter::new(File::create(path)?);
+        tree.set_trace(Box::new(writer), args.trace_format);
+    }
 
     while tree.root_index.is_none() {
-        let pair_info = paired_values_heap.pop_max().unwrap();
+        let ((value, _), pair) = paired_values_heap
+            .pop_max()
+            .expect("No pair is left to connect the remaining clusters");
         tree.updating(
-            pair_info.1.from_index,
-            pair_info.1.to_index,
+            value.0,
+            pair.from_index,
+            pair.to_index,
             &mut paired_values_heap,
             &paired_values_dict,
         )
     }
 
-    let out_str = tree.to_newick();
-    println!("{}", out_str);
+    for m in tree.sparse_merges().iter().filter(|_| main) {
+        let members = tree.index_node_dict[&m.node_index]
+            .data
+            .iter()
+            .map(|x| **x)
+            .collect::<Vec<_>>()
+            .join(",");
+        eprintln!(
+            "Sparse merge of ({}) on {} of {} pairs",
+            members, m.present, m.total
+        );
+    }
+
+    Ok((tree, paired_values_dict))
+}
+
+/// Counting the bipartitions of `n` replicate trees of `--bootstrap` or
+/// `--jackknife` drawn from the columns, the replicates with more
+/// missing pairs than allowed are skipped and reported
+fn count_replicates<'content>(
+    tree: &BinaryT<'content>,
+    columns: &Columns<'content>,
+    n: usize,
+    args: &Args,
+) -> io::Result<SplitCounter<'content>> {
+    let mut rng = SplitMix64::new(args.seed);
+    let keep = (columns.width() as f64 * (1.0 - args.jackknife_fraction)).round() as usize;
+    assert!(
+        args.jackknife.is_none() || (keep > 0 && keep < columns.width()),
+        "Deleting {} of the {} columns keeps {} of them, a jackknife replicate must delete some columns and keep some",
+        args.jackknife_fraction,
+        columns.width(),
+        keep
+    );
+    let mut counter = SplitCounter::new(tree);
+    // A replicate may draw no comparable site for some pairs
+    let mut skipped = 0;
+    for _ in 0..n {
+        let weights = if args.bootstrap.is_some() {
+            bootstrap_weights(&mut rng, columns.width())
+        } else {
+            jackknife_weights(&mut rng, columns.width(), keep)
+        };
+        let rows = columns.rows(&weights);
+        let missing = rows.iter().filter(|r| r.2.is_none()).count();
+        if missing as f64 / rows.len() as f64 > args.max_missing {
+            skipped += 1;
+            continue;
+        }
+        let (replicate, _) = build_tree(rows, args, false)?;
+        counter.add(&replicate);
+    }
+    if skipped > 0 {
+        eprintln!(
+            "Skipped {} of {} replicates with more missing pairs than allowed",
+            skipped, n
+        );
+    }
+    assert!(
+        counter.trees() > 0,
+        "No replicate is left to count the supports from"
+    );
+    Ok(counter)
+}
+
+fn clustering(args: &Args) -> io::Result<()> {
+    //let mut content = "s1 s2 -2\ns1 s3 -5\ns1 s4 -7\ns1 s5 -9\ns2 s3 -4\ns2 s4 -6\ns2 s5 -7\ns3 s4 -4\ns3 s5 -6\ns4 s5 -3\n".to_string();
+    let mapping_content = match &args.relabel {
+        Some(path) => read_file(path)?,
+        None => String::new(),
+    };
+    let mapping = args
+        .relabel
+        .as_ref()
+        .map(|_| parse_mapping(&mapping_content));
+    let input = args
+        .tsv
+        .as_ref()
+        .or(args.features.as_ref())
+        .or(args.alignment.as_ref())
+        .unwrap();
+    let content = read_file(input)?;
+    let columns = read_columns(&content, args);
+    let (mut tree, mut paired_values_dict) =
+        build_tree(input_rows(&content, &columns, args), args, true)?;
+
+    let mut labels = tree.leaf_labels();
+    labels.sort_unstable();
+    let needs_distance = args.check_metric
+        || args.cluster_quality
+        || args.silhouettes.is_some()
+        || args.sweep_k.is_some();
+    let distance = if needs_distance {
+        dense_matrix(&paired_values_dict, &labels)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|v| v.map(|v| args.self_value - v))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    } else {
+        vec![]
+    };
+
+    if args.check_metric {
+        let options = MetricOptions {
+            tolerance: args.metric_tolerance,
+            report: args.report_violations,
+            max_tuples: args.max_tuples,
+            seed: args.seed,
+        };
+        for r in check_metric(&labels, &distance, &options) {
+            eprintln!(
+                "The {} is violated by {} of {} {}{}",
+                r.name,
+                r.violated,
+                r.checked,
+                if r.sampled { "sampled " } else { "" },
+                if r.size == 3 { "triplets" } else { "quartets" }
+            );
+            for v in r.worst.iter() {
+                eprintln!("{}\t{}", v.excess, v.samples.join("\t"));
+            }
+        }
+    }
+
+    if args.cluster_quality || args.silhouettes.is_some() {
+        let roots = match (args.cut_height, args.cut_k) {
+            (Some(h), _) => tree.cut_at_height(h),
+            (None, Some(k)) => tree.cut_into(k),
+            (None, None) => unreachable!(),
+        };
+        let cluster = assign_clusters(&tree, &labels, &roots);
+        let q = cluster_quality(&distance, &cluster, roots.len());
+        if args.cluster_quality {
+            eprintln!(
+                "{} clusters, mean silhouette {}, Dunn index {}, Davies-Bouldin index {}",
+                q.k, q.silhouette, q.dunn, q.davies_bouldin
+            );
+        }
+        if let Some(path) = &args.silhouettes {
+            let mut out = String::from("label\tcluster\tsilhouette\n");
+            for (i, l) in labels.iter().enumerate() {
+                out.push_str(&format!(
+                    "{}\t{}\t{}\n",
+                    l,
+                    cluster[i] + 1,
+                    q.silhouettes[i]
+                ));
+            }
+            wirte_file(path, &out)?;
+        }
+    }
+    if let Some(max) = args.sweep_k {
+        eprintln!("k\tsilhouette\tdunn\tdavies_bouldin");
+        for q in sweep_k(&tree, &labels, &distance, max) {
+            eprintln!(
+                "{}\t{}\t{}\t{}",
+                q.k, q.silhouette, q.dunn, q.davies_bouldin
+            );
+        }
+    }
+
+    if let Some(path) = &args.compare {
+        let other_content = read_file(path)?;
+        let other_columns = read_columns(&other_content, args);
+        let other_rows = input_rows(&other_content, &other_columns, args);
+        let (other, _) = build_tree(other_rows, args, false)?;
+        let c = tree.compare(&other);
+        eprintln!(
+            "Compared on {} shared leafs, {} leafs are only in {} and {} only in {}",
+            c.shared.len(),
+            c.only_self,
+            input,
+            c.only_other,
+            path
+        );
+        eprintln!("splits\t{}\t{}", c.splits.0, c.splits.1);
+        eprintln!("robinson_foulds\t{}", c.robinson_foulds);
+        eprintln!(
+            "robinson_foulds_normalized\t{}",
+            c.robinson_foulds_normalized
+        );
+        eprintln!("matching_split\t{}", c.matching_split);
+        eprintln!("cophenetic_correlation\t{}", c.cophenetic_correlation);
+        eprintln!("bakers_gamma\t{}", c.bakers_gamma);
+    }
+
+    if let Some(label) = &args.root_leaf {
+        tree.reroot_at_leaf(label);
+    } else if args.midpoint_root {
+        tree.midpoint_root();
+    } else if !args.outgroup.is_empty() {
+        let labels = args.outgroup.iter().map(|l| l.as_str()).collect::<Vec<_>>();
+        tree.outgroup_root(&labels);
+    }
+    if let Some(label) = &args.clades_of {
+        let clades = tree.clades_of(label);
+        assert!(!clades.is_empty(), "The leaf {} does not exist", label);
+        eprintln!("height\tsize\tleafs");
+        for idx in clades {
+            eprintln!(
+                "{}\t{}\t{}",
+                tree.height(idx),
+                tree.size(idx),
+                tree.members(idx).join(",")
+            );
+        }
+    }
+    if !args.clade.is_empty() {
+        let labels = args.clade.iter().map(|l| l.as_str()).collect::<Vec<_>>();
+        let lca = tree
+            .lca(&labels)
+            .unwrap_or_else(|| panic!("Not all of the leafs {} exist", labels.join(",")));
+        eprintln!(
+            "The common ancestor of {} has height {} and {} leafs",
+            labels.join(","),
+            tree.height(lca),
+            tree.size(lca)
+        );
+        tree = tree.subtree(lca);
+    }
+    if let Some(path) = &args.prune_to {
+        let labels = read_file(path)?;
+        let keep = labels
+            .lines()
+            .map(|l| l.trim_end_matches('\r'))
+            .filter(|l| !l.is_empty())
+            .collect::<HashSet<_>>();
+        let removed = tree.prune_to(&keep);
+        eprintln!(
+            "Pruned {} leafs, {} leafs are left",
+            removed,
+            tree.leaf_labels().len()
+        );
+    }
+    // Before any consensus, which keeps the leaf order but may not be binary
+    if args.optimal_leaf_order {
+        let total = tree.optimal_leaf_ordering(&paired_values_dict);
+        eprintln!("Sum of adjacent leaf distances after ordering is {}", total);
+    }
+    if let (Some(c), Some(n)) = (&columns, args.bootstrap.or(args.jackknife)) {
+        let counter = count_replicates(&tree, c, n, args)?;
+        tree.set_supports(&counter);
+        eprintln!(
+            "Supports from {} {} replicates",
+            counter.trees(),
+            if args.bootstrap.is_some() {
+                "bootstrap"
+            } else {
+                "jackknife"
+            }
+        );
+        if let Some(rule) = args.consensus {
+            tree = counter.consensus(rule, args.self_value);
+        }
+    }
+    if args.sort_labels {
+        tree.sort_by_label();
+    }
+    if let Some(side) = args.ladderize {
+        tree.ladderize(side);
+    }
+    if let Some(length) = args.collapse_length {
+        let removed = tree.collapse_shorter_than(length);
+        eprintln!("Collapsed {} inner branches", removed);
+    }
+    if let Some(support) = args.collapse_support {
+        let removed = tree.collapse_unsupported(support);
+        eprintln!(
+            "Collapsed {} inner branches with support below {}",
+            removed, support
+        );
+    }
+    if let Some(mapping) = &mapping {
+        let renamed = tree.relabel(mapping);
+        eprintln!(
+            "Relabelled {} of {} leafs",
+            renamed,
+            tree.leaf_labels().len()
+        );
+        paired_values_dict = relabel_pairs(&paired_values_dict, mapping);
+    }
+    if let Some(path) = &args.leaf_order {
+        let mut labels = tree.leaf_labels().join("\n");
+        labels.push('\n');
+        wirte_file(path, &labels)?;
+    }
+
+    let mut metadata = match &args.leaf_metadata {
+        Some(path) => Some(LeafMetadata::from_tsv(&read_file(path)?)),
+        None => None,
+    };
+    if let (Some(m), Some(mapping)) = (&mut metadata, &mapping) {
+        m.relabel(mapping);
+    }
+    let xml_options = XmlOptions {
+        branch_lengths: args.branch_lengths,
+        heights: args.annotate,
+        metadata: metadata.as_ref(),
+        support_type: if args.jackknife.is_some() {
+            "jackknife"
+        } else {
+            "bootstrap"
+        },
+    };
+    let out_str = match args.format {
+        OutputFormat::Newick => tree.to_newick(args.branch_lengths),
+        OutputFormat::Nexus => to_nexus(
+            &tree,
+            &NexusOptions {
+                branch_lengths: args.branch_lengths,
+                annotations: args.annotate,
+            },
+        ),
+        OutputFormat::Phyloxml => to_phyloxml(&tree, &xml_options),
+        OutputFormat::Nexml => to_nexml(&tree, &xml_options),
+        OutputFormat::Json => to_json(&tree),
+        OutputFormat::JsonFlat => to_json_flat(&tree),
+        OutputFormat::Ascii => {
+            let width = args.width.unwrap_or_else(|| {
+                std::env::var("COLUMNS")
+                    .ok()
+                    .and_then(|c| c.parse::<usize>().ok())
+                    .unwrap_or(80)
+            });
+            to_ascii(
+                &tree,
+                &AsciiOptions {
+                    width,
+                    axis: args.axis,
+                },
+            )
+        }
+        OutputFormat::Dot => to_dot(&tree),
+        OutputFormat::Svg => {
+            let clusters = match (args.cut_height, args.cut_k) {
+                (Some(h), _) => tree.cut_at_height(h),
+                (None, Some(k)) => tree.cut_into(k),
+                (None, None) => vec![],
+            };
+            let colour_by = match (&metadata, &args.colour_by) {
+                (Some(m), Some(key)) => Some((m, key.as_str())),
+                (_, _) => None,
+            };
+            to_svg(
+                &tree,
+                &SvgOptions {
+                    layout: args.layout,
+                    font_size: args.font_size,
+                    size: args.svg_size,
+                    colour_by,
+                    clusters,
+                },
+            )
+        }
+    };
+    if let Some(path) = &args.heatmap {
+        let heatmap = if path.ends_with(".pgm") {
+            to_heatmap_pgm(&tree, &paired_values_dict, args.cell_size)
+        } else {
+            to_heatmap_svg(
+                &tree,
+                &paired_values_dict,
+                &HeatmapOptions {
+                    cell: args.cell_size as f64,
+                    dendrogram: 120.0,
+                    font_size: args.font_size,
+                },
+            )
+        };
+        wirte_file(path, &heatmap)?;
+    }
+    match &args.output {
+        Some(path) => wirte_file(path, &out_str)?,
+        None => println!("{}", out_str.trim_end()),
+    }
     Ok(())
 }
 
 fn main() -> io::Result<()> {
-    let tsv = std::env::args().nth(1).expect("Must give a tsv file.");
-    clustering(&tsv)?;
+    let args = Args::parse();
+    clustering(&args)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replicates_of_a_seed_are_reproducible() {
+        // Most of the pairs differ at the same number of sites
+        let content = ">a\nAAAAAAAA\n>b\nAAAAAAAC\n>c\nAAAAAACA\n>d\nAAAAACAA\n\
+                       >e\nCCAAAAAA\n>f\nCACAAAAA\n>g\nACCAAAAA\n";
+        let args = Args::parse_from([
+            "UPGMA",
+            "--alignment",
+            "-",
+            "--bootstrap",
+            "20",
+            "--seed",
+            "7",
+        ]);
+        let columns = read_columns(content, &args);
+        let newick = || {
+            let rows = input_rows(content, &columns, &args);
+            let (mut tree, _) = build_tree(rows, &args, false).unwrap();
+            let counter = count_replicates(&tree, columns.as_ref().unwrap(), 20, &args).unwrap();
+            tree.set_supports(&counter);
+            let consensus = counter.consensus(Consensus::Majority, args.self_value);
+            (tree.to_newick(true), consensus.to_newick(true))
+        };
+        let first = newick();
+        for _ in 0..5 {
+            assert_eq!(newick(), first);
+        }
+    }
+}